@@ -2,7 +2,7 @@
 
 use std::{env, error::Error};
 
-fn main() -> Result<(), Box<Error>> {
+fn main() -> Result<(), Box<dyn Error>> {
     let target = env::var("TARGET")?;
 
     if target.starts_with("thumbv6m-") {
@@ -15,5 +15,11 @@ fn main() -> Result<(), Box<Error>> {
         println!("cargo:rustc-cfg=armv7r");
     }
 
+    // these targets lack the instructions to implement CAS loops; the `mpmc` and `pool` modules
+    // fall back to critical sections on them
+    if target.starts_with("thumbv6m-") || target.starts_with("msp430-") {
+        println!("cargo:rustc-cfg=no_cas");
+    }
+
     Ok(())
 }
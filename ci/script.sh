@@ -3,10 +3,19 @@ set -euxo pipefail
 main() {
     cargo check --target $TARGET
     cargo check --target $TARGET --features 'serde'
+    cargo check --target $TARGET --features 'cache-padded'
+    cargo check --target $TARGET --features 'pool-stats'
+    if [ $TARGET != thumbv6m-none-eabi ] && [ $TARGET != msp430-none-elf ]; then
+        cargo check --target $TARGET --features 'async'
+    fi
 
     if [ $TARGET = x86_64-unknown-linux-gnu ]; then
         cargo test --target $TARGET --features 'serde'
         cargo test --target $TARGET --release --features 'serde'
+        cargo test --target $TARGET --release --features 'cache-padded'
+        cargo test --target $TARGET --release --features 'async'
+        cargo test --target $TARGET --release --features 'std'
+        cargo test --target $TARGET --release --features 'pool-stats'
 
         if [ $TRAVIS_RUST_VERSION = nightly ]; then
             export RUSTFLAGS="-Z sanitizer=thread"
@@ -0,0 +1,94 @@
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Waker,
+};
+
+// the waker slot is not being accessed
+const WAITING: usize = 0;
+// `register` is updating the waker slot
+const REGISTERING: usize = 0b01;
+// `wake` is taking the waker out of the slot
+const WAKING: usize = 0b10;
+
+/// A slot that holds the `Waker` of the task that's waiting on the other end of a data structure
+///
+/// `register` and `wake` can be called concurrently from different execution contexts: a `wake`
+/// that races with a `register` wakes the newly registered waker, so no wake up is lost.
+// NOTE this is the algorithm used by the `AtomicWaker` of the `futures` crate
+pub(crate) struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+impl AtomicWaker {
+    pub(crate) const fn new() -> Self {
+        AtomicWaker {
+            state: AtomicUsize::new(WAITING),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Registers `waker` to be woken up by the next `wake` call
+    ///
+    /// This replaces the previously registered waker. It must not be called concurrently with
+    /// itself; only the waiting side calls it.
+    pub(crate) fn register(&self, waker: &Waker) {
+        match self
+            .state
+            .compare_exchange(WAITING, REGISTERING, Ordering::Acquire, Ordering::Acquire)
+            .unwrap_or_else(|state| state)
+        {
+            WAITING => unsafe {
+                let slot = &mut *self.waker.get();
+                match slot {
+                    Some(old) if old.will_wake(waker) => {}
+                    _ => *slot = Some(waker.clone()),
+                }
+
+                // a concurrent `wake` may have set the WAKING bit while we were updating the slot;
+                // in that case it left the waker to us
+                if self
+                    .state
+                    .compare_exchange(REGISTERING, WAITING, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    let waker = (*self.waker.get()).take();
+                    self.state.swap(WAITING, Ordering::AcqRel);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            },
+
+            // a `wake` is in progress; wake up the task right away so it polls again
+            WAKING => waker.wake_by_ref(),
+
+            // concurrent `register` calls; not supported
+            _ => {}
+        }
+    }
+
+    /// Wakes up the registered waker, if any
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = self.take() {
+            waker.wake();
+        }
+    }
+
+    fn take(&self) -> Option<Waker> {
+        match self.state.fetch_or(WAKING, Ordering::AcqRel) {
+            WAITING => {
+                let waker = unsafe { (*self.waker.get()).take() };
+                self.state.fetch_and(!WAKING, Ordering::Release);
+                waker
+            }
+
+            // a `register` (or another `wake`) is in progress; it will see the WAKING bit
+            _ => None,
+        }
+    }
+}
+
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
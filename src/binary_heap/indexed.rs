@@ -0,0 +1,577 @@
+use core::{fmt, marker::PhantomData, ptr, slice, usize};
+
+use generic_array::{ArrayLength, GenericArray};
+
+use crate::{binary_heap::Hole, sealed::binary_heap::Kind};
+
+// end of the free slot list
+const NONE: usize = usize::MAX;
+
+/// A handle to an item stored in an [`IndexedBinaryHeap`](struct.IndexedBinaryHeap.html)
+///
+/// Handles are invalidated when their item is removed from the heap. Using a stale handle is not
+/// unsafe; the heap will report that it doesn't contain the item.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Handle {
+    slot: usize,
+    generation: u32,
+}
+
+#[doc(hidden)]
+#[derive(Clone, Copy, Default)]
+pub struct Slot {
+    // position of the item in the heap if the slot is in use; otherwise the next free slot
+    pos: usize,
+    // bumped every time the slot is vacated to invalidate outstanding handles
+    generation: u32,
+}
+
+/// A priority queue whose items can be reprioritized or removed after they have been pushed
+///
+/// `push` returns a [`Handle`] that can later be used to look up, reprioritize or remove the item
+/// in **O(log n)** time, which is what algorithms like Dijkstra's shortest path or timer
+/// rescheduling need.
+///
+/// [`Handle`]: struct.Handle.html
+///
+/// This can be either a min-heap or a max-heap, and items are ordered using the heap kind (`Min`,
+/// `Max`, `MinBy` or `MaxBy`).
+///
+/// ```
+/// use heapless::binary_heap::{IndexedBinaryHeap, Min};
+/// use heapless::consts::*;
+///
+/// let mut heap: IndexedBinaryHeap<_, U8, Min> = IndexedBinaryHeap::new();
+///
+/// let a = heap.push(30).unwrap();
+/// let b = heap.push(20).unwrap();
+/// let c = heap.push(10).unwrap();
+///
+/// // decrease key
+/// heap.change_priority(a, |x| *x = 5).unwrap();
+/// assert_eq!(heap.peek(), Some(&5));
+///
+/// // cancel
+/// assert_eq!(heap.remove(c), Some(10));
+/// assert!(!heap.contains(c));
+///
+/// assert_eq!(heap.pop(), Some(5));
+/// assert_eq!(heap.get(b), Some(&20));
+/// assert_eq!(heap.pop(), Some(20));
+/// assert_eq!(heap.pop(), None);
+/// ```
+pub struct IndexedBinaryHeap<T, N, KIND>(
+    #[doc(hidden)]
+    pub  crate::i::IndexedBinaryHeap<GenericArray<(T, usize), N>, GenericArray<Slot, N>, KIND>,
+)
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    KIND: Kind<T>;
+
+impl<A, S, K> crate::i::IndexedBinaryHeap<A, S, K> {
+    /// `IndexedBinaryHeap` `const` constructor; wrap the returned value in
+    /// [`IndexedBinaryHeap`](../binary_heap/struct.IndexedBinaryHeap.html)
+    pub const fn new() -> Self {
+        Self {
+            _kind: PhantomData,
+            data: crate::i::Vec::new(),
+            slots: crate::i::Vec::new(),
+            free: NONE,
+        }
+    }
+}
+
+impl<T, N, K> crate::i::IndexedBinaryHeap<GenericArray<(T, usize), N>, GenericArray<Slot, N>, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+{
+    pub(crate) fn clear(&mut self) {
+        while self.data.len != 0 {
+            let (_, slot) = unsafe { self.data.pop_unchecked() };
+            self.release(slot);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.data.len
+    }
+
+    pub(crate) fn iter(&self) -> IndexedIter<'_, T> {
+        IndexedIter {
+            iter: self.data.as_slice().iter(),
+        }
+    }
+
+    pub(crate) fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle)
+            .map(|pos| unsafe { &self.data.as_slice().get_unchecked(pos).0 })
+    }
+
+    pub(crate) fn peek(&self) -> Option<&T> {
+        self.data.as_slice().first().map(|(item, _)| item)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        if self.data.len == 0 {
+            None
+        } else {
+            Some(self.remove_at(0))
+        }
+    }
+
+    pub(crate) fn push(&mut self, item: T) -> Result<Handle, T> {
+        if self.data.is_full() {
+            return Err(item);
+        }
+
+        let pos = self.data.len;
+        let slot = if self.free != NONE {
+            let slot = self.free;
+            self.free = self.slots.as_slice()[slot].pos;
+            self.slots.as_mut_slice()[slot].pos = pos;
+            slot
+        } else {
+            // NOTE there are as many slots as items can be stored in the heap so there's always a
+            // fresh slot when the free list is empty and the heap is not full
+            let slot = self.slots.len;
+            unsafe {
+                self.slots.push_unchecked(Slot { pos, generation: 0 });
+            }
+            slot
+        };
+
+        unsafe { self.data.push_unchecked((item, slot)) }
+        self.sift_up(pos);
+
+        Ok(self.handle(slot))
+    }
+
+    pub(crate) fn change_priority<F>(&mut self, handle: Handle, f: F) -> Result<(), ()>
+    where
+        F: FnOnce(&mut T),
+    {
+        let pos = self.position(handle).ok_or(())?;
+
+        f(&mut self.data.as_mut_slice()[pos].0);
+
+        let pos = self.sift_up(pos);
+        self.sift_down(pos);
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.position(handle).map(|pos| self.remove_at(pos))
+    }
+
+    pub(crate) fn handle(&self, slot: usize) -> Handle {
+        Handle {
+            slot,
+            generation: self.slots.as_slice()[slot].generation,
+        }
+    }
+
+    pub(crate) fn position(&self, handle: Handle) -> Option<usize> {
+        let slot = *self.slots.as_slice().get(handle.slot)?;
+        match self.data.as_slice().get(slot.pos) {
+            Some(&(_, s)) if s == handle.slot && slot.generation == handle.generation => {
+                Some(slot.pos)
+            }
+            _ => None,
+        }
+    }
+
+    fn release(&mut self, slot: usize) {
+        let s = &mut self.slots.as_mut_slice()[slot];
+        s.generation = s.generation.wrapping_add(1);
+        s.pos = self.free;
+        self.free = slot;
+    }
+
+    fn remove_at(&mut self, pos: usize) -> T {
+        let last = self.data.len - 1;
+        self.data.as_mut_slice().swap(pos, last);
+        let (item, slot) = unsafe { self.data.pop_unchecked() };
+        self.release(slot);
+
+        if pos < last {
+            let moved = self.data.as_slice()[pos].1;
+            self.slots.as_mut_slice()[moved].pos = pos;
+            let pos = self.sift_up(pos);
+            self.sift_down(pos);
+        }
+
+        item
+    }
+
+    fn sift_up(&mut self, pos: usize) -> usize {
+        let slots = self.slots.as_mut_slice();
+        unsafe {
+            // Take out the value at `pos` and create a hole.
+            let mut hole = Hole::new(self.data.as_mut_slice(), pos);
+
+            while hole.pos() > 0 {
+                let parent = (hole.pos() - 1) / 2;
+                if K::cmp(&hole.element().0, &hole.get(parent).0) != K::ordering() {
+                    break;
+                }
+                let child = hole.pos();
+                hole.move_to(parent);
+                slots.get_unchecked_mut(hole.get(child).1).pos = child;
+            }
+
+            slots.get_unchecked_mut(hole.element().1).pos = hole.pos();
+            hole.pos()
+        }
+    }
+
+    fn sift_down(&mut self, pos: usize) {
+        let end = self.data.len;
+        let slots = self.slots.as_mut_slice();
+        unsafe {
+            let mut hole = Hole::new(self.data.as_mut_slice(), pos);
+            let mut child = 2 * pos + 1;
+            while child < end {
+                let right = child + 1;
+                // compare with the greater of the two children
+                if right < end && K::cmp(&hole.get(child).0, &hole.get(right).0) != K::ordering() {
+                    child = right;
+                }
+
+                // if we are already in order, stop
+                if K::cmp(&hole.element().0, &hole.get(child).0) == K::ordering() {
+                    break;
+                }
+
+                let parent = hole.pos();
+                hole.move_to(child);
+                slots.get_unchecked_mut(hole.get(parent).1).pos = parent;
+                child = 2 * hole.pos() + 1;
+            }
+
+            slots.get_unchecked_mut(hole.element().1).pos = hole.pos();
+        }
+    }
+}
+
+impl<T, N, K> IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+{
+    /* Constructors */
+    /// Creates an empty `IndexedBinaryHeap` as a $K-heap.
+    ///
+    /// ```
+    /// use heapless::binary_heap::{IndexedBinaryHeap, Max};
+    /// use heapless::consts::*;
+    ///
+    /// // allocate the binary heap on the stack
+    /// let mut heap: IndexedBinaryHeap<_, U8, Max> = IndexedBinaryHeap::new();
+    /// heap.push(4).unwrap();
+    ///
+    /// // allocate the binary heap in a static variable
+    /// static mut HEAP: IndexedBinaryHeap<i32, U8, Max> =
+    ///     IndexedBinaryHeap(heapless::i::IndexedBinaryHeap::new());
+    /// ```
+    pub fn new() -> Self {
+        IndexedBinaryHeap(crate::i::IndexedBinaryHeap::new())
+    }
+
+    /* Public API */
+    /// Returns the capacity of the binary heap.
+    pub fn capacity(&self) -> usize {
+        self.0.data.capacity()
+    }
+
+    /// Drops all items from the binary heap.
+    ///
+    /// This invalidates all the handles to the items in the heap.
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// Returns the length of the binary heap.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks if the binary heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator visiting all values in the underlying vector, in arbitrary order.
+    pub fn iter(&self) -> IndexedIter<'_, T> {
+        self.0.iter()
+    }
+
+    /// Returns `true` if `handle` refers to an item in the binary heap
+    ///
+    /// Computes in **O(1)** time
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.0.position(handle).is_some()
+    }
+
+    /// Returns a reference to the item that `handle` refers to, or `None` if it's no longer in the
+    /// binary heap
+    ///
+    /// Computes in **O(1)** time
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.0.get(handle)
+    }
+
+    /// Returns the *top* (greatest if max-heap, smallest if min-heap) item in the binary heap, or
+    /// None if it is empty.
+    pub fn peek(&self) -> Option<&T> {
+        self.0.peek()
+    }
+
+    /// Returns the handle of the *top* (greatest if max-heap, smallest if min-heap) item in the
+    /// binary heap, or None if it is empty.
+    pub fn peek_handle(&self) -> Option<Handle> {
+        self.0
+            .data
+            .as_slice()
+            .first()
+            .map(|&(_, slot)| self.0.handle(slot))
+    }
+
+    /// Removes the *top* (greatest if max-heap, smallest if min-heap) item from the binary heap and
+    /// returns it, or None if it is empty.
+    ///
+    /// Computes in **O(log n)** time
+    pub fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    /// Pushes an item onto the binary heap and returns a handle to it
+    ///
+    /// Returns back the `item` if the binary heap is full
+    ///
+    /// Computes in **O(log n)** time
+    pub fn push(&mut self, item: T) -> Result<Handle, T> {
+        self.0.push(item)
+    }
+
+    /// Changes the priority of the item that `handle` refers to
+    ///
+    /// `f` is used to modify the item in place; the item is then moved to its new position in the
+    /// binary heap. Returns an error if `handle` doesn't refer to an item in the binary heap.
+    ///
+    /// Computes in **O(log n)** time
+    pub fn change_priority<F>(&mut self, handle: Handle, f: F) -> Result<(), ()>
+    where
+        F: FnOnce(&mut T),
+    {
+        self.0.change_priority(handle, f)
+    }
+
+    /// Removes the item that `handle` refers to from the binary heap and returns it, or `None` if
+    /// it's no longer in the binary heap
+    ///
+    /// Computes in **O(log n)** time
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.0.remove(handle)
+    }
+}
+
+impl<T, N, K> Default for IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, N, K> Clone for IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        IndexedBinaryHeap(crate::i::IndexedBinaryHeap {
+            _kind: self.0._kind,
+            data: self.0.data.clone(),
+            slots: self.0.slots.clone(),
+            free: self.0.free,
+        })
+    }
+}
+
+impl<T, N, K> Drop for IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+{
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0.data.as_mut_slice()) }
+    }
+}
+
+impl<T, N, K> fmt::Debug for IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, N, K> IntoIterator for &'a IndexedBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, usize)> + ArrayLength<Slot>,
+    K: Kind<T>,
+{
+    type Item = &'a T;
+    type IntoIter = IndexedIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the items of an `IndexedBinaryHeap`, in arbitrary order
+pub struct IndexedIter<'a, T> {
+    iter: slice::Iter<'a, (T, usize)>,
+}
+
+impl<'a, T> Iterator for IndexedIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(item, _)| item)
+    }
+}
+
+impl<'a, T> Clone for IndexedIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, vec::Vec};
+
+    use crate::{
+        binary_heap::{IndexedBinaryHeap, Max, Min},
+        consts::*,
+    };
+
+    #[test]
+    fn static_new() {
+        static mut _H: IndexedBinaryHeap<i32, U16, Min> =
+            IndexedBinaryHeap(crate::i::IndexedBinaryHeap::new());
+    }
+
+    #[test]
+    fn push_pop() {
+        let mut heap = IndexedBinaryHeap::<_, U16, Min>::new();
+        for x in &[17, 1, 25, 3, 100, 2, 36, 7, 19] {
+            heap.push(*x).unwrap();
+        }
+
+        let mut popped = Vec::new();
+        while let Some(x) = heap.pop() {
+            popped.push(x);
+        }
+
+        assert_eq!(popped, [1, 2, 3, 7, 17, 19, 25, 36, 100]);
+    }
+
+    #[test]
+    fn change_priority() {
+        let mut heap = IndexedBinaryHeap::<_, U8, Max>::new();
+        let a = heap.push(1).unwrap();
+        let b = heap.push(2).unwrap();
+        let c = heap.push(3).unwrap();
+        let d = heap.push(4).unwrap();
+
+        // increase
+        heap.change_priority(a, |x| *x = 10).unwrap();
+        assert_eq!(heap.peek_handle(), Some(a));
+
+        // decrease
+        heap.change_priority(a, |x| *x = 0).unwrap();
+        assert_eq!(heap.peek_handle(), Some(d));
+
+        heap.change_priority(b, |x| *x = 5).unwrap();
+
+        assert_eq!(heap.pop(), Some(5));
+        assert_eq!(heap.pop(), Some(4));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(heap.pop(), None);
+
+        // stale handle
+        assert!(heap.change_priority(c, |x| *x = 7).is_err());
+    }
+
+    #[test]
+    fn remove() {
+        let mut heap = IndexedBinaryHeap::<_, U8, Min>::new();
+        let handles = [5, 3, 8, 1, 9, 2, 7]
+            .iter()
+            .map(|x| heap.push(*x).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(heap.remove(handles[2]), Some(8));
+        assert_eq!(heap.remove(handles[3]), Some(1));
+        assert_eq!(heap.remove(handles[3]), None);
+        assert_eq!(heap.len(), 5);
+
+        for (i, h) in handles.iter().enumerate() {
+            assert_eq!(heap.contains(*h), i != 2 && i != 3);
+        }
+
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(5));
+        assert_eq!(heap.pop(), Some(7));
+        assert_eq!(heap.pop(), Some(9));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn slot_reuse() {
+        let mut heap = IndexedBinaryHeap::<_, U2, Min>::new();
+        let a = heap.push(1).unwrap();
+        let b = heap.push(2).unwrap();
+        assert_eq!(heap.push(3), Err(3));
+
+        assert_eq!(heap.pop(), Some(1));
+        let c = heap.push(0).unwrap();
+
+        // `c` reuses the slot of `a` but `a` remains invalid
+        assert!(!heap.contains(a));
+        assert_eq!(heap.get(a), None);
+        assert_eq!(heap.get(b), Some(&2));
+        assert_eq!(heap.get(c), Some(&0));
+
+        heap.clear();
+        assert!(!heap.contains(b));
+        assert!(!heap.contains(c));
+    }
+
+    #[test]
+    fn drop() {
+        let rc = Rc::new(());
+        let mut heap = IndexedBinaryHeap::<_, U4, Max>::new();
+        heap.push(rc.clone()).unwrap();
+        heap.push(rc.clone()).unwrap();
+        let clone = heap.clone();
+        assert_eq!(Rc::strong_count(&rc), 5);
+
+        core::mem::drop(heap);
+        core::mem::drop(clone);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
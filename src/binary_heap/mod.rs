@@ -9,6 +9,7 @@
 // n)` in-place heapsort.
 
 use core::{
+    cmp::Ordering,
     fmt,
     marker::PhantomData,
     mem::{self, ManuallyDrop},
@@ -18,6 +19,15 @@ use core::{
 use generic_array::{ArrayLength, GenericArray};
 
 use crate::sealed::binary_heap::Kind;
+pub use indexed::{Handle, IndexedBinaryHeap, IndexedIter, Slot};
+pub(crate) use stable::Stable;
+pub use stable::{StableBinaryHeap, StableIter};
+pub(crate) use timer::Deadline;
+pub use timer::{PopExpired, TimerQueue};
+
+mod indexed;
+mod stable;
+mod timer;
 
 /// Min-heap
 pub enum Min {}
@@ -25,6 +35,60 @@ pub enum Min {}
 /// Max-heap
 pub enum Max {}
 
+/// Min-heap ordered by the comparator `C`
+///
+/// The item that `C` considers the smallest is at the top of the heap.
+pub struct MinBy<C> {
+    _comparator: PhantomData<C>,
+}
+
+/// Max-heap ordered by the comparator `C`
+///
+/// The item that `C` considers the greatest is at the top of the heap.
+pub struct MaxBy<C> {
+    _comparator: PhantomData<C>,
+}
+
+/// A comparison function used to order the items of a [`MinBy`] or [`MaxBy`] binary heap
+///
+/// [`MinBy`]: struct.MinBy.html
+/// [`MaxBy`]: struct.MaxBy.html
+///
+/// Items don't need to implement `Ord` to be stored in a heap ordered by a comparator.
+///
+/// ```
+/// use core::cmp::Ordering;
+///
+/// use heapless::binary_heap::{BinaryHeap, Compare, MinBy};
+/// use heapless::consts::*;
+///
+/// struct Task {
+///     deadline: u32,
+///     id: u8,
+/// }
+///
+/// struct ByDeadline;
+///
+/// impl Compare<Task> for ByDeadline {
+///     fn compare(a: &Task, b: &Task) -> Ordering {
+///         a.deadline.cmp(&b.deadline)
+///     }
+/// }
+///
+/// let mut heap: BinaryHeap<Task, U8, MinBy<ByDeadline>> = BinaryHeap::new();
+/// heap.push(Task { deadline: 30, id: 0 }).ok().unwrap();
+/// heap.push(Task { deadline: 10, id: 1 }).ok().unwrap();
+/// heap.push(Task { deadline: 20, id: 2 }).ok().unwrap();
+///
+/// assert_eq!(heap.pop().map(|task| task.id), Some(1));
+/// assert_eq!(heap.pop().map(|task| task.id), Some(2));
+/// assert_eq!(heap.pop().map(|task| task.id), Some(0));
+/// ```
+pub trait Compare<T> {
+    /// Returns the ordering of `a` relative to `b`
+    fn compare(a: &T, b: &T) -> Ordering;
+}
+
 impl<A, K> crate::i::BinaryHeap<A, K> {
     /// `BinaryHeap` `const` constructor; wrap the returned value in
     /// [`BinaryHeap`](../struct.BinaryHeap.html)
@@ -36,13 +100,76 @@ impl<A, K> crate::i::BinaryHeap<A, K> {
     }
 }
 
+impl<T, N, K> crate::i::BinaryHeap<GenericArray<T, N>, K>
+where
+    N: ArrayLength<T>,
+    K: Kind<T>,
+{
+    pub(crate) unsafe fn pop_unchecked(&mut self) -> T {
+        let mut item = self.data.pop_unchecked();
+
+        if self.data.len != 0 {
+            mem::swap(&mut item, self.data.as_mut_slice().get_unchecked_mut(0));
+            self.sift_down_to_bottom(0);
+        }
+        item
+    }
+
+    pub(crate) unsafe fn push_unchecked(&mut self, item: T) {
+        let old_len = self.data.len;
+        self.data.push_unchecked(item);
+        self.sift_up(0, old_len);
+    }
+
+    fn sift_down_to_bottom(&mut self, mut pos: usize) {
+        let end = self.data.len;
+        let start = pos;
+        unsafe {
+            let mut hole = Hole::new(self.data.as_mut_slice(), pos);
+            let mut child = 2 * pos + 1;
+            while child < end {
+                let right = child + 1;
+                // compare with the greater of the two children
+                if right < end && K::cmp(hole.get(child), hole.get(right)) != K::ordering() {
+                    child = right;
+                }
+                hole.move_to(child);
+                child = 2 * hole.pos() + 1;
+            }
+            pos = hole.pos;
+        }
+        self.sift_up(start, pos);
+    }
+
+    fn sift_up(&mut self, start: usize, pos: usize) -> usize {
+        unsafe {
+            // Take out the value at `pos` and create a hole.
+            let mut hole = Hole::new(self.data.as_mut_slice(), pos);
+
+            while hole.pos() > start {
+                let parent = (hole.pos() - 1) / 2;
+                if K::cmp(hole.element(), hole.get(parent)) != K::ordering() {
+                    break;
+                }
+                hole.move_to(parent);
+            }
+            hole.pos()
+        }
+    }
+}
+
 /// A priority queue implemented with a binary heap.
 ///
-/// This can be either a min-heap or a max-heap.
+/// This can be either a min-heap or a max-heap. Items are ordered according to their `Ord`
+/// implementation (`Min` and `Max`) or according to a [`Compare`] implementation (`MinBy` and
+/// `MaxBy`).
+///
+/// [`Compare`]: trait.Compare.html
 ///
 /// It is a logic error for an item to be modified in such a way that the item's ordering relative
-/// to any other item, as determined by the `Ord` trait, changes while it is in the heap. This is
-/// normally only possible through `Cell`, `RefCell`, global state, I/O, or unsafe code.
+/// to any other item, as determined by the `Ord` trait or the comparator, changes while it is in
+/// the heap. This is normally only possible through `Cell`, `RefCell`, global state, I/O, or
+/// unsafe code.
 ///
 /// ```
 /// use heapless::binary_heap::{BinaryHeap, Max};
@@ -87,15 +214,13 @@ pub struct BinaryHeap<T, N, KIND>(
     #[doc(hidden)] pub crate::i::BinaryHeap<GenericArray<T, N>, KIND>,
 )
 where
-    T: Ord,
     N: ArrayLength<T>,
-    KIND: Kind;
+    KIND: Kind<T>;
 
 impl<T, N, K> BinaryHeap<T, N, K>
 where
-    T: Ord,
     N: ArrayLength<T>,
-    K: Kind,
+    K: Kind<T>,
 {
     /* Constructors */
     /// Creates an empty BinaryHeap as a $K-heap.
@@ -252,13 +377,7 @@ where
     /// Removes the *top* (greatest if max-heap, smallest if min-heap) item from the binary heap and
     /// returns it, without checking if the binary heap is empty.
     pub unsafe fn pop_unchecked(&mut self) -> T {
-        let mut item = self.0.data.pop_unchecked();
-
-        if !self.is_empty() {
-            mem::swap(&mut item, self.0.data.as_mut_slice().get_unchecked_mut(0));
-            self.sift_down_to_bottom(0);
-        }
-        item
+        self.0.pop_unchecked()
     }
 
     /// Pushes an item onto the binary heap.
@@ -286,46 +405,7 @@ where
 
     /// Pushes an item onto the binary heap without first checking if it's full.
     pub unsafe fn push_unchecked(&mut self, item: T) {
-        let old_len = self.len();
-        self.0.data.push_unchecked(item);
-        self.sift_up(0, old_len);
-    }
-
-    /* Private API */
-    fn sift_down_to_bottom(&mut self, mut pos: usize) {
-        let end = self.len();
-        let start = pos;
-        unsafe {
-            let mut hole = Hole::new(self.0.data.as_mut_slice(), pos);
-            let mut child = 2 * pos + 1;
-            while child < end {
-                let right = child + 1;
-                // compare with the greater of the two children
-                if right < end && hole.get(child).cmp(hole.get(right)) != K::ordering() {
-                    child = right;
-                }
-                hole.move_to(child);
-                child = 2 * hole.pos() + 1;
-            }
-            pos = hole.pos;
-        }
-        self.sift_up(start, pos);
-    }
-
-    fn sift_up(&mut self, start: usize, pos: usize) -> usize {
-        unsafe {
-            // Take out the value at `pos` and create a hole.
-            let mut hole = Hole::new(self.0.data.as_mut_slice(), pos);
-
-            while hole.pos() > start {
-                let parent = (hole.pos() - 1) / 2;
-                if hole.element().cmp(hole.get(parent)) != K::ordering() {
-                    break;
-                }
-                hole.move_to(parent);
-            }
-            hole.pos()
-        }
+        self.0.push_unchecked(item)
     }
 }
 
@@ -403,9 +483,8 @@ impl<'a, T> Drop for Hole<'a, T> {
 
 impl<T, N, K> Default for BinaryHeap<T, N, K>
 where
-    T: Ord,
     N: ArrayLength<T>,
-    K: Kind,
+    K: Kind<T>,
 {
     fn default() -> Self {
         Self::new()
@@ -415,8 +494,8 @@ where
 impl<T, N, K> Clone for BinaryHeap<T, N, K>
 where
     N: ArrayLength<T>,
-    K: Kind,
-    T: Ord + Clone,
+    K: Kind<T>,
+    T: Clone,
 {
     fn clone(&self) -> Self {
         BinaryHeap(crate::i::BinaryHeap {
@@ -429,8 +508,7 @@ where
 impl<T, N, K> Drop for BinaryHeap<T, N, K>
 where
     N: ArrayLength<T>,
-    K: Kind,
-    T: Ord,
+    K: Kind<T>,
 {
     fn drop(&mut self) {
         unsafe { ptr::drop_in_place(self.0.data.as_mut_slice()) }
@@ -440,8 +518,8 @@ where
 impl<T, N, K> fmt::Debug for BinaryHeap<T, N, K>
 where
     N: ArrayLength<T>,
-    K: Kind,
-    T: Ord + fmt::Debug,
+    K: Kind<T>,
+    T: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_list().entries(self.iter()).finish()
@@ -451,8 +529,7 @@ where
 impl<'a, T, N, K> IntoIterator for &'a BinaryHeap<T, N, K>
 where
     N: ArrayLength<T>,
-    K: Kind,
-    T: Ord,
+    K: Kind<T>,
 {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
@@ -466,8 +543,10 @@ where
 mod tests {
     use std::vec::Vec;
 
+    use core::cmp::Ordering;
+
     use crate::{
-        binary_heap::{self, BinaryHeap, Min},
+        binary_heap::{self, BinaryHeap, Compare, MaxBy, Min, MinBy},
         consts::*,
     };
 
@@ -547,4 +626,39 @@ mod tests {
         assert_eq!(heap.pop(), Some(1));
         assert_eq!(heap.pop(), None);
     }
+
+    #[test]
+    fn by_comparator() {
+        struct Reverse;
+
+        impl Compare<i32> for Reverse {
+            fn compare(a: &i32, b: &i32) -> Ordering {
+                b.cmp(a)
+            }
+        }
+
+        let mut heap = BinaryHeap::<_, U16, MinBy<Reverse>>::new();
+        heap.push(1).unwrap();
+        heap.push(17).unwrap();
+        heap.push(3).unwrap();
+        heap.push(25).unwrap();
+
+        assert_eq!(heap.pop(), Some(25));
+        assert_eq!(heap.pop(), Some(17));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), None);
+
+        let mut heap = BinaryHeap::<_, U16, MaxBy<Reverse>>::new();
+        heap.push(1).unwrap();
+        heap.push(17).unwrap();
+        heap.push(3).unwrap();
+        heap.push(25).unwrap();
+
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(17));
+        assert_eq!(heap.pop(), Some(25));
+        assert_eq!(heap.pop(), None);
+    }
 }
@@ -0,0 +1,371 @@
+use core::{fmt, marker::PhantomData, ptr, slice, u32};
+
+use generic_array::{ArrayLength, GenericArray};
+
+use crate::sealed::binary_heap::Kind;
+
+/// Heap kind that breaks ties between equal items using their insertion sequence number
+pub(crate) struct Stable<K> {
+    _kind: PhantomData<K>,
+}
+
+impl<A, K> crate::i::StableBinaryHeap<A, K> {
+    /// `StableBinaryHeap` `const` constructor; wrap the returned value in
+    /// [`StableBinaryHeap`](../binary_heap/struct.StableBinaryHeap.html)
+    pub const fn new() -> Self {
+        Self {
+            heap: crate::i::BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+}
+
+/// A priority queue that returns items of equal priority in first-in, first-out order
+///
+/// This is a [`BinaryHeap`] that tags each item with an insertion sequence number, which is used
+/// to break ties between items that compare as equal. Each item uses an extra `u32` of storage.
+///
+/// [`BinaryHeap`]: struct.BinaryHeap.html
+///
+/// Items are compared using the heap kind (`Min`, `Max`, `MinBy` or `MaxBy`); items that compare
+/// as equal are returned in the order they were pushed.
+///
+/// ```
+/// use core::cmp::Ordering;
+///
+/// use heapless::binary_heap::{Compare, MaxBy, StableBinaryHeap};
+/// use heapless::consts::*;
+///
+/// struct ByPriority;
+///
+/// impl Compare<(u8, char)> for ByPriority {
+///     fn compare(a: &(u8, char), b: &(u8, char)) -> Ordering {
+///         a.0.cmp(&b.0)
+///     }
+/// }
+///
+/// let mut heap: StableBinaryHeap<_, U8, MaxBy<ByPriority>> = StableBinaryHeap::new();
+///
+/// heap.push((0, 'a')).unwrap();
+/// heap.push((1, 'b')).unwrap();
+/// heap.push((0, 'c')).unwrap();
+/// heap.push((1, 'd')).unwrap();
+///
+/// assert_eq!(heap.pop(), Some((1, 'b')));
+/// assert_eq!(heap.pop(), Some((1, 'd')));
+/// assert_eq!(heap.pop(), Some((0, 'a')));
+/// assert_eq!(heap.pop(), Some((0, 'c')));
+/// assert_eq!(heap.pop(), None);
+/// ```
+pub struct StableBinaryHeap<T, N, KIND>(
+    #[doc(hidden)] pub crate::i::StableBinaryHeap<GenericArray<(T, u32), N>, KIND>,
+)
+where
+    N: ArrayLength<(T, u32)>,
+    KIND: Kind<T>;
+
+impl<T, N, K> StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+{
+    /* Constructors */
+    /// Creates an empty `StableBinaryHeap` as a $K-heap.
+    ///
+    /// ```
+    /// use heapless::binary_heap::{Max, StableBinaryHeap};
+    /// use heapless::consts::*;
+    ///
+    /// // allocate the binary heap on the stack
+    /// let mut heap: StableBinaryHeap<_, U8, Max> = StableBinaryHeap::new();
+    /// heap.push(4).unwrap();
+    ///
+    /// // allocate the binary heap in a static variable
+    /// static mut HEAP: StableBinaryHeap<i32, U8, Max> =
+    ///     StableBinaryHeap(heapless::i::StableBinaryHeap::new());
+    /// ```
+    pub fn new() -> Self {
+        StableBinaryHeap(crate::i::StableBinaryHeap::new())
+    }
+
+    /* Public API */
+    /// Returns the capacity of the binary heap.
+    pub fn capacity(&self) -> usize {
+        self.0.heap.data.capacity()
+    }
+
+    /// Drops all items from the binary heap.
+    pub fn clear(&mut self) {
+        self.0.heap.data.clear();
+        self.0.seq = 0;
+    }
+
+    /// Returns the length of the binary heap.
+    pub fn len(&self) -> usize {
+        self.0.heap.data.len
+    }
+
+    /// Checks if the binary heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator visiting all values in the underlying vector, in arbitrary order.
+    pub fn iter(&self) -> StableIter<'_, T> {
+        StableIter {
+            iter: self.0.heap.data.as_slice().iter(),
+        }
+    }
+
+    /// Returns the *top* (greatest if max-heap, smallest if min-heap) item in the binary heap, or
+    /// None if it is empty.
+    ///
+    /// If several items compare as equal to the top item, the one that was pushed first is
+    /// returned.
+    pub fn peek(&self) -> Option<&T> {
+        self.0.heap.data.as_slice().get(0).map(|(item, _)| item)
+    }
+
+    /// Removes the *top* (greatest if max-heap, smallest if min-heap) item from the binary heap and
+    /// returns it, or None if it is empty.
+    ///
+    /// If several items compare as equal to the top item, the one that was pushed first is
+    /// removed.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(unsafe { self.pop_unchecked() })
+        }
+    }
+
+    /// Removes the *top* (greatest if max-heap, smallest if min-heap) item from the binary heap and
+    /// returns it, without checking if the binary heap is empty.
+    pub unsafe fn pop_unchecked(&mut self) -> T {
+        self.0.heap.pop_unchecked().0
+    }
+
+    /// Pushes an item onto the binary heap.
+    ///
+    /// Returns back the `item` if the binary heap is full
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        if self.0.heap.data.is_full() {
+            return Err(item);
+        }
+
+        unsafe { self.push_unchecked(item) }
+        Ok(())
+    }
+
+    /// Pushes an item onto the binary heap without first checking if it's full.
+    pub unsafe fn push_unchecked(&mut self, item: T) {
+        if self.0.seq == u32::MAX {
+            self.renumber();
+        }
+
+        let seq = self.0.seq;
+        self.0.seq += 1;
+        self.0.heap.push_unchecked((item, seq));
+    }
+
+    /* Private API */
+    // Replaces the sequence numbers of the items in the heap with `0..len`, preserving their
+    // relative order. This doesn't change the heap order so no sifting is required
+    //
+    // This is `O(n^2)` but only runs once every 2^32 pushes
+    fn renumber(&mut self) {
+        let entries = self.0.heap.data.as_mut_slice();
+
+        // NOTE the `k`-th oldest entry gets `k` as its new sequence number, which is never greater
+        // than its old sequence number, so `prev` is an upper bound for the renumbered entries
+        let mut prev = None;
+        for new in 0..entries.len() {
+            let mut oldest = None;
+            for i in 0..entries.len() {
+                let seq = entries[i].1;
+
+                if prev.map(|prev| seq > prev).unwrap_or(true)
+                    && oldest.map(|j: usize| seq < entries[j].1).unwrap_or(true)
+                {
+                    oldest = Some(i);
+                }
+            }
+
+            if let Some(i) = oldest {
+                prev = Some(entries[i].1);
+                entries[i].1 = new as u32;
+            }
+        }
+
+        self.0.seq = entries.len() as u32;
+    }
+}
+
+impl<T, N, K> Default for StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, N, K> Clone for StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        StableBinaryHeap(crate::i::StableBinaryHeap {
+            heap: crate::i::BinaryHeap {
+                _kind: self.0.heap._kind,
+                data: self.0.heap.data.clone(),
+            },
+            seq: self.0.seq,
+        })
+    }
+}
+
+impl<T, N, K> Drop for StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+{
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0.heap.data.as_mut_slice()) }
+    }
+}
+
+impl<T, N, K> fmt::Debug for StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, N, K> IntoIterator for &'a StableBinaryHeap<T, N, K>
+where
+    N: ArrayLength<(T, u32)>,
+    K: Kind<T>,
+{
+    type Item = &'a T;
+    type IntoIter = StableIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the items of a `StableBinaryHeap`, in arbitrary order
+pub struct StableIter<'a, T> {
+    iter: slice::Iter<'a, (T, u32)>,
+}
+
+impl<'a, T> Iterator for StableIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(item, _)| item)
+    }
+}
+
+impl<'a, T> Clone for StableIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{cmp::Ordering, u32};
+
+    use crate::{
+        binary_heap::{Compare, MaxBy, MinBy, StableBinaryHeap},
+        consts::*,
+    };
+
+    struct ByKey;
+
+    impl Compare<(u8, char)> for ByKey {
+        fn compare(a: &(u8, char), b: &(u8, char)) -> Ordering {
+            a.0.cmp(&b.0)
+        }
+    }
+
+    #[test]
+    fn static_new() {
+        static mut _B: StableBinaryHeap<(u8, char), U16, MinBy<ByKey>> =
+            StableBinaryHeap(crate::i::StableBinaryHeap::new());
+    }
+
+    #[test]
+    fn fifo() {
+        let mut heap = StableBinaryHeap::<_, U16, MinBy<ByKey>>::new();
+        heap.push((2, 'a')).unwrap();
+        heap.push((1, 'b')).unwrap();
+        heap.push((2, 'c')).unwrap();
+        heap.push((1, 'd')).unwrap();
+        heap.push((2, 'e')).unwrap();
+        heap.push((1, 'f')).unwrap();
+
+        assert_eq!(heap.peek(), Some(&(1, 'b')));
+        assert_eq!(heap.pop(), Some((1, 'b')));
+        assert_eq!(heap.pop(), Some((1, 'd')));
+        assert_eq!(heap.pop(), Some((1, 'f')));
+        assert_eq!(heap.pop(), Some((2, 'a')));
+        assert_eq!(heap.pop(), Some((2, 'c')));
+        assert_eq!(heap.pop(), Some((2, 'e')));
+        assert_eq!(heap.pop(), None);
+
+        let mut heap = StableBinaryHeap::<_, U16, MaxBy<ByKey>>::new();
+        heap.push((1, 'a')).unwrap();
+        heap.push((2, 'b')).unwrap();
+        heap.push((1, 'c')).unwrap();
+        heap.push((2, 'd')).unwrap();
+
+        assert_eq!(heap.pop(), Some((2, 'b')));
+        assert_eq!(heap.pop(), Some((2, 'd')));
+        assert_eq!(heap.pop(), Some((1, 'a')));
+        assert_eq!(heap.pop(), Some((1, 'c')));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn sequence_wrap_around() {
+        let mut heap = StableBinaryHeap::<_, U4, MinBy<ByKey>>::new();
+        heap.0.seq = u32::MAX - 3;
+
+        heap.push((1, 'a')).unwrap();
+        heap.push((0, 'b')).unwrap();
+        heap.push((1, 'c')).unwrap();
+        // sequence numbers are renumbered at this point
+        heap.push((1, 'd')).unwrap();
+        assert_eq!(heap.0.seq, 4);
+
+        assert_eq!(heap.pop(), Some((0, 'b')));
+        heap.push((0, 'e')).unwrap();
+
+        assert_eq!(heap.pop(), Some((0, 'e')));
+        assert_eq!(heap.pop(), Some((1, 'a')));
+        assert_eq!(heap.pop(), Some((1, 'c')));
+        assert_eq!(heap.pop(), Some((1, 'd')));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn full() {
+        let mut heap = StableBinaryHeap::<_, U2, MinBy<ByKey>>::new();
+        heap.push((0, 'a')).unwrap();
+        heap.push((0, 'b')).unwrap();
+
+        assert_eq!(heap.push((0, 'c')), Err((0, 'c')));
+    }
+}
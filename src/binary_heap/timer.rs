@@ -0,0 +1,299 @@
+use core::{cmp::Ordering, fmt, ptr};
+
+use generic_array::{ArrayLength, GenericArray};
+
+use crate::{
+    binary_heap::{Compare, Handle, Slot},
+    i,
+    sealed::binary_heap::Tick,
+};
+
+// orders timers by deadline; see `Tick` for how wraparound is handled
+pub(crate) struct Deadline;
+
+impl<I, T> Compare<(I, T)> for Deadline
+where
+    I: Tick,
+{
+    fn compare(a: &(I, T), b: &(I, T)) -> Ordering {
+        I::wrapping_cmp(a.0, b.0)
+    }
+}
+
+/// A fixed capacity queue of timers ordered by deadline
+///
+/// Each timer is a `(deadline, payload)` pair. `I` is the tick counter type, which can be `u32`
+/// (default) or `u64`.
+///
+/// Deadlines are compared in a wraparound-safe manner: a deadline is considered to be in the
+/// future if it's less than half the counter range (2^31 ticks for `u32`) ahead of the other
+/// deadline or of `now`. Thus all the scheduled deadlines must be within that distance of the
+/// current tick count for the queue to work correctly.
+///
+/// ```
+/// use heapless::binary_heap::TimerQueue;
+/// use heapless::consts::*;
+///
+/// let mut timers: TimerQueue<&str, U8> = TimerQueue::new();
+///
+/// // the tick counter is about to wrap around
+/// let now = u32::max_value() - 10;
+///
+/// timers.schedule(now.wrapping_add(20), "b").unwrap();
+/// timers.schedule(now.wrapping_add(5), "a").unwrap();
+/// let c = timers.schedule(now.wrapping_add(15), "c").unwrap();
+///
+/// assert_eq!(timers.next_deadline(), Some(now + 5));
+///
+/// assert_eq!(timers.cancel(c), Some("c"));
+///
+/// // nothing has expired yet
+/// assert_eq!(timers.pop_expired(now).next(), None);
+///
+/// // after the tick counter wraps around
+/// let now = now.wrapping_add(30);
+/// let mut expired = timers.pop_expired(now);
+/// assert_eq!(expired.next(), Some((u32::max_value() - 5, "a")));
+/// assert_eq!(expired.next(), Some((9, "b")));
+/// assert_eq!(expired.next(), None);
+/// ```
+pub struct TimerQueue<T, N, I = u32>(
+    #[doc(hidden)] pub i::TimerQueue<GenericArray<((I, T), usize), N>, GenericArray<Slot, N>>,
+)
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick;
+
+impl<A, S> crate::i::TimerQueue<A, S> {
+    /// `TimerQueue` `const` constructor; wrap the returned value in
+    /// [`TimerQueue`](../binary_heap/struct.TimerQueue.html)
+    pub const fn new() -> Self {
+        Self {
+            heap: crate::i::IndexedBinaryHeap::new(),
+        }
+    }
+}
+
+impl<T, N, I> TimerQueue<T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick,
+{
+    /// Creates an empty timer queue
+    ///
+    /// ```
+    /// use heapless::binary_heap::TimerQueue;
+    /// use heapless::consts::*;
+    ///
+    /// // allocate the timer queue in a static variable
+    /// static mut TIMERS: TimerQueue<u8, U8> = TimerQueue(heapless::i::TimerQueue::new());
+    /// ```
+    pub fn new() -> Self {
+        TimerQueue(crate::i::TimerQueue::new())
+    }
+
+    /// Returns the maximum number of timers the queue can hold
+    pub fn capacity(&self) -> usize {
+        self.0.heap.data.capacity()
+    }
+
+    /// Cancels all the timers
+    pub fn clear(&mut self) {
+        self.0.heap.clear()
+    }
+
+    /// Returns the number of scheduled timers
+    pub fn len(&self) -> usize {
+        self.0.heap.len()
+    }
+
+    /// Returns `true` if there are no scheduled timers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Schedules a timer that expires at `deadline`
+    ///
+    /// Returns a handle that can be used to cancel or reschedule the timer, or returns back the
+    /// `payload` if the queue is full
+    ///
+    /// Computes in **O(log n)** time
+    pub fn schedule(&mut self, deadline: I, payload: T) -> Result<Handle, T> {
+        self.0
+            .heap
+            .push((deadline, payload))
+            .map_err(|(_, payload)| payload)
+    }
+
+    /// Changes the deadline of a scheduled timer
+    ///
+    /// Returns an error if the timer has already expired or has been cancelled
+    ///
+    /// Computes in **O(log n)** time
+    pub fn reschedule(&mut self, handle: Handle, deadline: I) -> Result<(), ()> {
+        self.0
+            .heap
+            .change_priority(handle, |timer| timer.0 = deadline)
+    }
+
+    /// Cancels a scheduled timer and returns its payload
+    ///
+    /// Returns `None` if the timer has already expired or has been cancelled
+    ///
+    /// Computes in **O(log n)** time
+    pub fn cancel(&mut self, handle: Handle) -> Option<T> {
+        self.0.heap.remove(handle).map(|(_, payload)| payload)
+    }
+
+    /// Returns `true` if the timer is still scheduled
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.0.heap.position(handle).is_some()
+    }
+
+    /// Returns the earliest deadline, or `None` if there are no scheduled timers
+    ///
+    /// Computes in **O(1)** time
+    pub fn next_deadline(&self) -> Option<I> {
+        self.0.heap.peek().map(|&(deadline, _)| deadline)
+    }
+
+    /// Returns an iterator that removes and yields, as `(deadline, payload)` pairs, all the timers
+    /// that have expired at `now`, earliest deadline first
+    ///
+    /// A timer has expired if its deadline is not after `now`. Timers that are not yielded, because
+    /// the iterator was dropped before it was exhausted, remain in the queue.
+    pub fn pop_expired(&mut self, now: I) -> PopExpired<'_, T, N, I> {
+        PopExpired { timers: self, now }
+    }
+}
+
+impl<T, N, I> Default for TimerQueue<T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, N, I> Drop for TimerQueue<T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick,
+{
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0.heap.data.as_mut_slice()) }
+    }
+}
+
+impl<T, N, I> fmt::Debug for TimerQueue<T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick + fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.heap.iter()).finish()
+    }
+}
+
+/// An iterator that removes the expired timers from a [`TimerQueue`](struct.TimerQueue.html)
+pub struct PopExpired<'a, T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick,
+{
+    timers: &'a mut TimerQueue<T, N, I>,
+    now: I,
+}
+
+impl<'a, T, N, I> Iterator for PopExpired<'a, T, N, I>
+where
+    N: ArrayLength<((I, T), usize)> + ArrayLength<Slot>,
+    I: Tick,
+{
+    type Item = (I, T);
+
+    fn next(&mut self) -> Option<(I, T)> {
+        let deadline = self.timers.next_deadline()?;
+
+        if I::wrapping_cmp(deadline, self.now) != Ordering::Greater {
+            self.timers.0.heap.pop()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use crate::{binary_heap::TimerQueue, consts::*};
+
+    #[test]
+    fn static_new() {
+        static mut _T: TimerQueue<char, U8> = TimerQueue(crate::i::TimerQueue::new());
+    }
+
+    #[test]
+    fn expire_in_order() {
+        let mut timers: TimerQueue<char, U8> = TimerQueue::new();
+        timers.schedule(30, 'c').unwrap();
+        timers.schedule(10, 'a').unwrap();
+        timers.schedule(20, 'b').unwrap();
+        timers.schedule(40, 'd').unwrap();
+
+        assert_eq!(timers.pop_expired(9).next(), None);
+        assert_eq!(
+            timers.pop_expired(30).collect::<Vec<_>>(),
+            [(10, 'a'), (20, 'b'), (30, 'c')]
+        );
+        assert_eq!(timers.next_deadline(), Some(40));
+    }
+
+    #[test]
+    fn cancel_and_reschedule() {
+        let mut timers: TimerQueue<char, U8, u64> = TimerQueue::new();
+        let a = timers.schedule(10, 'a').unwrap();
+        let b = timers.schedule(20, 'b').unwrap();
+        let c = timers.schedule(30, 'c').unwrap();
+
+        timers.reschedule(c, 5).unwrap();
+        assert_eq!(timers.cancel(a), Some('a'));
+        assert_eq!(timers.cancel(a), None);
+
+        assert_eq!(
+            timers.pop_expired(25).collect::<Vec<_>>(),
+            [(5, 'c'), (20, 'b')]
+        );
+        assert!(!timers.contains(b));
+        assert!(timers.reschedule(b, 50).is_err());
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut timers: TimerQueue<char, U8> = TimerQueue::new();
+        let now = u32::max_value() - 1;
+        timers.schedule(now.wrapping_add(3), 'c').unwrap();
+        timers.schedule(now.wrapping_add(1), 'a').unwrap();
+        timers.schedule(now.wrapping_add(2), 'b').unwrap();
+
+        assert_eq!(timers.next_deadline(), Some(u32::max_value()));
+        assert_eq!(timers.pop_expired(now).next(), None);
+        assert_eq!(
+            timers.pop_expired(now.wrapping_add(2)).collect::<Vec<_>>(),
+            [(u32::max_value(), 'a'), (0, 'b')]
+        );
+        assert_eq!(timers.next_deadline(), Some(1));
+    }
+
+    #[test]
+    fn full() {
+        let mut timers: TimerQueue<char, U1> = TimerQueue::new();
+        timers.schedule(0, 'a').unwrap();
+        assert_eq!(timers.schedule(0, 'b'), Err('b'));
+    }
+}
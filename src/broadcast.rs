@@ -0,0 +1,467 @@
+//! Fixed capacity Single Producer Multiple Consumer broadcast queue
+//!
+//! Every reader gets *every* item written to the queue, at its own pace. The writer never waits
+//! for the readers: once the queue is full the writer overwrites the oldest item and the readers
+//! that had not read it yet are told how many items they missed with an
+//! [`Overrun`](struct.Overrun.html) error.
+//!
+//! # Examples
+//!
+//! ```
+//! use heapless::{broadcast::{Overrun, Queue}, consts::*};
+//!
+//! let mut q: Queue<u16, U4> = Queue::new();
+//!
+//! let (mut writer, mut logger) = q.split();
+//! let mut radio = logger.clone();
+//!
+//! writer.write(0);
+//! writer.write(1);
+//!
+//! // each reader has its own cursor
+//! assert_eq!(logger.read(), Ok(Some(0)));
+//! assert_eq!(logger.read(), Ok(Some(1)));
+//! assert_eq!(logger.read(), Ok(None));
+//!
+//! writer.write(2);
+//! writer.write(3);
+//! writer.write(4);
+//!
+//! // the `radio` reader fell behind; items `0` and `1` have been overwritten
+//! assert_eq!(radio.read(), Err(Overrun(2)));
+//! assert_eq!(radio.read(), Ok(Some(2)));
+//! assert_eq!(radio.read(), Ok(Some(3)));
+//! assert_eq!(radio.read(), Ok(Some(4)));
+//! assert_eq!(radio.read(), Ok(None));
+//! ```
+//!
+//! # Algorithm
+//!
+//! The writer index uses the same `Atomic<U, C>` machinery as [`spsc::Queue`]; the readers keep
+//! their own cursors so the writer doesn't need to know about them. A reader copies an item out of
+//! the buffer and then checks, seqlock style, that the writer didn't start overwriting the item
+//! while it was being copied. For this reason the items must be `Copy` and a reader can lag at
+//! most `N - 1` items behind the writer: the slot after the newest item is the one the writer will
+//! overwrite next.
+//!
+//! [`spsc::Queue`]: ../spsc/struct.Queue.html
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{self, Ordering},
+};
+
+use generic_array::{typenum::PowerOfTwo, ArrayLength, GenericArray};
+
+use crate::{
+    sealed::spsc as sealed,
+    spsc::{slot, Atomic, MultiCore, SingleCore},
+};
+
+/// The reader fell behind the writer by more than `N - 1` items
+///
+/// The inner value is the number of items the reader skipped. The reader has been moved to the
+/// oldest item still in the queue.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Overrun(pub usize);
+
+/// A statically allocated single writer, multiple reader broadcast queue with a capacity of `N`
+/// elements
+///
+/// *IMPORTANT*: To get better performance use a capacity that is a power of 2 (e.g. `U16`, `U32`,
+/// etc.).
+///
+/// Like [`spsc::Queue`](../spsc/struct.Queue.html), the index type is generic and can be changed to
+/// `u8` or `u16` using the [`u8`] and [`u16`] constructors. With these smaller index types `N`
+/// must be a power of 2, which is checked at compile time, so that the wrapping index maps to the
+/// buffer slots without a discontinuity. The number of skipped items reported by [`Overrun`] is
+/// then only correct modulo `256` / `65536` and `N` must be smaller than these values.
+///
+/// The single core variants (`u8_sc`, `u16_sc`, `usize_sc` and `new_sc`) are `unsafe` to create
+/// because the programmer must make sure that the writer and all the readers are kept on a single
+/// core for their entire lifetime.
+///
+/// [`u8`]: struct.Queue.html#method.u8
+/// [`u16`]: struct.Queue.html#method.u16
+/// [`Overrun`]: struct.Overrun.html
+pub struct Queue<T, N, U = usize, C = MultiCore>(
+    #[doc(hidden)] pub crate::i::BroadcastQueue<GenericArray<T, N>, U, C>,
+)
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore;
+
+impl<T, N, U, C> Queue<T, N, U, C>
+where
+    T: Copy,
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    /// Returns the maximum number of elements the queue can hold
+    pub fn capacity(&self) -> usize {
+        N::to_usize()
+    }
+
+    /// Splits the queue into its writer and a reader end points
+    ///
+    /// The reader starts at the end of the queue; i.e. it will only see items written after this
+    /// call. Clone the reader to get more readers.
+    pub fn split<'rb>(&'rb mut self) -> (Writer<'rb, T, N, U, C>, Reader<'rb, T, N, U, C>) {
+        let pos = self.0.tail.load_relaxed();
+
+        (Writer { rb: self }, Reader { rb: self, pos })
+    }
+
+    fn slot(&self, index: U) -> *mut T {
+        unsafe { (self.0.buffer.get() as *mut T).add(slot::<N>(index.into())) }
+    }
+}
+
+impl<T, N, U, C> fmt::Debug for Queue<T, N, U, C>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("capacity", &N::to_usize())
+            .finish()
+    }
+}
+
+/// The writer end point of a broadcast queue
+pub struct Writer<'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    rb: &'a Queue<T, N, U, C>,
+}
+
+unsafe impl<'a, T, N, U, C> Send for Writer<'a, T, N, U, C>
+where
+    N: ArrayLength<T>,
+    T: Send,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+}
+
+/// A reader end point of a broadcast queue
+///
+/// Each reader has its own cursor. Cloning a reader creates a new reader that starts at the same
+/// position.
+pub struct Reader<'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    rb: &'a Queue<T, N, U, C>,
+    pos: U,
+}
+
+unsafe impl<'a, T, N, U, C> Send for Reader<'a, T, N, U, C>
+where
+    N: ArrayLength<T>,
+    T: Send,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+}
+
+impl<'a, T, N, U, C> Clone for Reader<'a, T, N, U, C>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    fn clone(&self) -> Self {
+        Reader {
+            rb: self.rb,
+            pos: self.pos,
+        }
+    }
+}
+
+// A memory barrier when the queue is shared between cores; a compiler barrier otherwise
+fn fence<C>(order: Ordering)
+where
+    C: sealed::XCore,
+{
+    if C::is_multi_core() {
+        atomic::fence(order)
+    } else {
+        atomic::compiler_fence(order)
+    }
+}
+
+// NOTE `$bound` is `PowerOfTwo` for the indices that wrap around before `usize` does; with any
+// other `N`, `index % N` is discontinuous at the wrap around point
+macro_rules! impl_ {
+    ($uxx:ident, $uxx_sc:ident $(, $bound:ident)?) => {
+        impl<T, N> Queue<T, N, $uxx, MultiCore>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+        {
+            /// Creates an empty queue with a fixed capacity of `N`
+            pub fn $uxx() -> Self {
+                Queue(crate::i::BroadcastQueue::$uxx())
+            }
+        }
+
+        impl<A> crate::i::BroadcastQueue<A, $uxx, MultiCore> {
+            /// `broadcast::Queue` `const` constructor; wrap the returned value in
+            /// [`broadcast::Queue`](struct.Queue.html)
+            pub const fn $uxx() -> Self {
+                crate::i::BroadcastQueue {
+                    tail: Atomic::new(0),
+                    buffer: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }
+        }
+
+        impl<T, N> Queue<T, N, $uxx, SingleCore>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+        {
+            /// Creates an empty queue with a fixed capacity of `N` (single core variant)
+            pub unsafe fn $uxx_sc() -> Self {
+                Queue(crate::i::BroadcastQueue::$uxx_sc())
+            }
+        }
+
+        impl<A> crate::i::BroadcastQueue<A, $uxx, SingleCore> {
+            /// `broadcast::Queue` `const` constructor; wrap the returned value in
+            /// [`broadcast::Queue`](struct.Queue.html)
+            pub const unsafe fn $uxx_sc() -> Self {
+                crate::i::BroadcastQueue {
+                    tail: Atomic::new(0),
+                    buffer: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }
+        }
+
+        impl<'a, T, N, C> Writer<'a, T, N, $uxx, C>
+        where
+            T: Copy,
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Adds an `item` to the end of the queue, overwriting the oldest item if the queue is
+            /// full
+            pub fn write(&mut self, item: T) {
+                let tail = self.rb.0.tail.load_relaxed();
+
+                // NOTE(fence) readers that observe the (partial) write below must also observe the
+                // previous `tail` store so they can tell that the slot is being overwritten
+                fence::<C>(Ordering::Release);
+                unsafe { ptr::write_volatile(self.rb.slot(tail), item) }
+                self.rb.0.tail.store_release(tail.wrapping_add(1)); // ▲
+            }
+        }
+
+        impl<'a, T, N, C> Reader<'a, T, N, $uxx, C>
+        where
+            T: Copy,
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Returns the next item in the queue, `Ok(None)` if the reader has seen every item,
+            /// or an [`Overrun`](struct.Overrun.html) error if the reader fell too far behind the
+            /// writer
+            ///
+            /// After an overrun the reader continues from the oldest item still in the queue.
+            pub fn read(&mut self) -> Result<Option<T>, Overrun> {
+                let tail = self.rb.0.tail.load_acquire(); // ▼
+                let lag = usize::from(tail.wrapping_sub(self.pos));
+
+                if lag == 0 {
+                    return Ok(None);
+                } else if lag >= N::to_usize() {
+                    return Err(self.skip(tail));
+                }
+
+                // NOTE the copy may be torn if the writer is overwriting the slot at the same time
+                // so it must not be interpreted as a `T` until it has been validated below
+                let item =
+                    unsafe { ptr::read_volatile(self.rb.slot(self.pos) as *const MaybeUninit<T>) };
+
+                fence::<C>(Ordering::Acquire);
+                let tail = self.rb.0.tail.load_relaxed();
+                if usize::from(tail.wrapping_sub(self.pos)) >= N::to_usize() {
+                    return Err(self.skip(tail));
+                }
+
+                self.pos = self.pos.wrapping_add(1);
+                Ok(Some(unsafe { item.assume_init() }))
+            }
+
+            /// Returns the number of items this reader has yet to read
+            ///
+            /// This can be larger than the capacity of the queue if the reader fell behind the
+            /// writer; the next `read` will then report an overrun.
+            pub fn len(&self) -> usize {
+                let tail = self.rb.0.tail.load_relaxed();
+                usize::from(tail.wrapping_sub(self.pos))
+            }
+
+            /// Returns `true` if this reader has read every item
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            // Moves the cursor to the oldest item that's not being overwritten
+            fn skip(&mut self, tail: $uxx) -> Overrun {
+                let pos = tail.wrapping_sub(N::to_usize() as $uxx - 1);
+                let skipped = usize::from(pos.wrapping_sub(self.pos));
+                self.pos = pos;
+                Overrun(skipped)
+            }
+        }
+    };
+}
+
+impl<A> crate::i::BroadcastQueue<A, usize, MultiCore> {
+    /// `broadcast::Queue` `const` constructor; wrap the returned value in
+    /// [`broadcast::Queue`](struct.Queue.html)
+    pub const fn new() -> Self {
+        crate::i::BroadcastQueue::usize()
+    }
+}
+
+impl<T, N> Queue<T, N, usize, MultiCore>
+where
+    N: ArrayLength<T>,
+{
+    /// Alias for [`broadcast::Queue::usize`](struct.Queue.html#method.usize)
+    pub fn new() -> Self {
+        Queue(crate::i::BroadcastQueue::new())
+    }
+}
+
+impl<A> crate::i::BroadcastQueue<A, usize, SingleCore> {
+    /// `broadcast::Queue` `const` constructor; wrap the returned value in
+    /// [`broadcast::Queue`](struct.Queue.html)
+    pub const unsafe fn new_sc() -> Self {
+        crate::i::BroadcastQueue::usize_sc()
+    }
+}
+
+impl<T, N> Queue<T, N, usize, SingleCore>
+where
+    N: ArrayLength<T>,
+{
+    /// Alias for [`broadcast::Queue::usize_sc`](struct.Queue.html#method.usize_sc)
+    pub unsafe fn new_sc() -> Self {
+        Queue(crate::i::BroadcastQueue::new_sc())
+    }
+}
+
+impl_!(u8, u8_sc, PowerOfTwo);
+impl_!(u16, u16_sc, PowerOfTwo);
+impl_!(usize, usize_sc);
+
+#[cfg(test)]
+mod tests {
+    use crate::consts::*;
+
+    use super::{Overrun, Queue};
+
+    #[test]
+    fn sanity() {
+        let mut q: Queue<u8, U4> = Queue::new();
+        assert_eq!(q.capacity(), 4);
+
+        let (mut w, mut r) = q.split();
+        assert!(r.is_empty());
+
+        w.write(0);
+        w.write(1);
+        assert_eq!(r.len(), 2);
+
+        assert_eq!(r.read(), Ok(Some(0)));
+        assert_eq!(r.read(), Ok(Some(1)));
+        assert_eq!(r.read(), Ok(None));
+    }
+
+    #[test]
+    fn independent_readers() {
+        let mut q: Queue<u8, U4> = Queue::new();
+        let (mut w, mut r1) = q.split();
+
+        w.write(0);
+        let mut r2 = r1.clone();
+
+        assert_eq!(r1.read(), Ok(Some(0)));
+        assert_eq!(r1.read(), Ok(None));
+
+        w.write(1);
+        assert_eq!(r1.read(), Ok(Some(1)));
+        assert_eq!(r2.read(), Ok(Some(0)));
+        assert_eq!(r2.read(), Ok(Some(1)));
+        assert_eq!(r2.read(), Ok(None));
+    }
+
+    #[test]
+    fn overrun() {
+        let mut q: Queue<u8, U4> = Queue::new();
+        let (mut w, mut r) = q.split();
+
+        // at most `N - 1` items can be read back
+        for i in 0..3 {
+            w.write(i);
+        }
+        assert_eq!(r.clone().read(), Ok(Some(0)));
+
+        w.write(3);
+        assert_eq!(r.read(), Err(Overrun(1)));
+        assert_eq!(r.read(), Ok(Some(1)));
+
+        for i in 4..20 {
+            w.write(i);
+        }
+        assert_eq!(r.read(), Err(Overrun(15)));
+        assert_eq!(r.read(), Ok(Some(17)));
+        assert_eq!(r.read(), Ok(Some(18)));
+        assert_eq!(r.read(), Ok(Some(19)));
+        assert_eq!(r.read(), Ok(None));
+    }
+
+    #[test]
+    fn u8_wrap_around() {
+        let mut q: Queue<u16, U8, u8> = Queue::u8();
+        let (mut w, mut r) = q.split();
+
+        for i in 0..1000 {
+            w.write(i);
+            w.write(i);
+            assert_eq!(r.read(), Ok(Some(i)));
+            assert_eq!(r.read(), Ok(Some(i)));
+            assert_eq!(r.read(), Ok(None));
+        }
+
+        for i in 0..10 {
+            w.write(i);
+        }
+        assert_eq!(r.read(), Err(Overrun(3)));
+        assert_eq!(r.read(), Ok(Some(3)));
+    }
+
+    #[test]
+    fn const_new() {
+        const Q: Queue<i32, U4> = Queue(crate::i::BroadcastQueue::new());
+
+        let mut q = Q;
+        let (mut w, mut r) = q.split();
+        w.write(-1);
+        assert_eq!(r.read(), Ok(Some(-1)));
+    }
+}
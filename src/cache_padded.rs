@@ -0,0 +1,66 @@
+use core::ops;
+
+/// Pads and aligns a value to the size of a cache line, when the `cache-padded` feature is enabled
+///
+/// This prevents false sharing between values that are written by different cores, like the head
+/// and tail indices of a queue. When the feature is disabled this is a zero cost wrapper.
+// NOTE x86_64 and AArch64 prefetch cache lines in pairs so we align to two 64-byte lines there
+#[cfg_attr(
+    all(
+        feature = "cache-padded",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    all(
+        feature = "cache-padded",
+        not(any(target_arch = "x86_64", target_arch = "aarch64"))
+    ),
+    repr(align(64))
+)]
+pub(crate) struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        CachePadded { value }
+    }
+}
+
+impl<T> ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> ops::DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{mem, sync::atomic::AtomicUsize};
+
+    use super::CachePadded;
+
+    #[test]
+    fn layout() {
+        struct Indices {
+            _head: CachePadded<AtomicUsize>,
+            _tail: CachePadded<AtomicUsize>,
+        }
+
+        if cfg!(feature = "cache-padded") {
+            assert!(mem::align_of::<CachePadded<AtomicUsize>>() >= 64);
+            assert!(mem::size_of::<Indices>() >= 128);
+        } else {
+            assert_eq!(mem::size_of::<Indices>(), 2 * mem::size_of::<usize>());
+        }
+    }
+}
@@ -81,3 +81,51 @@
 //! let (p, c) = rb.split();
 //! rb.enqueue(0).unwrap();
 //! ```
+//!
+//! # `broadcast` capacity
+//!
+//! With `u8` / `u16` indices the capacity of a `broadcast::Queue` must be a power of 2
+//!
+//! ``` compile_fail
+//! use heapless::{broadcast::Queue, consts::*};
+//!
+//! let mut q: Queue<u32, U3, u8> = Queue::u8();
+//! ```
+//!
+//! ``` compile_fail
+//! use heapless::{broadcast::Queue, consts::*};
+//!
+//! let mut q: Queue<u32, U3, u8> = Queue(heapless::i::BroadcastQueue::u8());
+//! let (mut w, _) = q.split();
+//! w.write(0);
+//! ```
+//!
+//! # `spsc` capacity
+//!
+//! With `u8` / `u16` indices the capacity of a `spsc::Queue` must be a power of 2
+//!
+//! ``` compile_fail
+//! use heapless::{consts::*, spsc::Queue};
+//!
+//! let mut q: Queue<u32, U3, u8> = Queue::u8();
+//! ```
+//!
+//! ``` compile_fail
+//! use heapless::{consts::*, spsc::Queue};
+//!
+//! let mut q: Queue<u32, U3, u8> = Queue(heapless::i::Queue::u8());
+//! let (mut p, _) = q.split();
+//! p.enqueue(0);
+//! ```
+//!
+//! # `spsc::Consumer::drain`
+//!
+//! Forgetting a `Drain` leaves the yielded items in the queue so the items must be `Copy`
+//!
+//! ``` compile_fail
+//! use heapless::{consts::*, spsc::Queue};
+//!
+//! let mut rb: Queue<Box<u8>, U4> = Queue::new();
+//! let (_, mut c) = rb.split();
+//! c.drain();
+//! ```
@@ -0,0 +1,22 @@
+//! Interrupt-free critical sections for targets that lack compare-and-swap (CAS) instructions
+//!
+//! These targets are single core so disabling interrupts is enough to make a load-compare-store
+//! sequence atomic with respect to every other context.
+
+/// Executes the closure `f` with interrupts disabled
+#[cfg(armv6m)]
+pub(crate) fn free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    cortex_m::interrupt::free(|_| f())
+}
+
+/// Executes the closure `f` with interrupts disabled
+#[cfg(target_arch = "msp430")]
+pub(crate) fn free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    msp430::interrupt::free(|_| f())
+}
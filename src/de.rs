@@ -14,9 +14,9 @@ use crate::{
 
 impl<'de, T, N, KIND> Deserialize<'de> for BinaryHeap<T, N, KIND>
 where
-    T: Ord + Deserialize<'de>,
+    T: Deserialize<'de>,
     N: ArrayLength<T>,
-    KIND: BinaryHeapKind,
+    KIND: BinaryHeapKind<T>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -26,9 +26,9 @@ where
 
         impl<'de, T, N, KIND> de::Visitor<'de> for ValueVisitor<'de, T, N, KIND>
         where
-            T: Ord + Deserialize<'de>,
+            T: Deserialize<'de>,
             N: ArrayLength<T>,
-            KIND: BinaryHeapKind,
+            KIND: BinaryHeapKind<T>,
         {
             type Value = BinaryHeap<T, N, KIND>;
 
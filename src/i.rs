@@ -1,8 +1,27 @@
 //! Unfortunate implementation detail required to construct `heapless` types in const context
 
-use core::{marker::PhantomData, mem::MaybeUninit};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use crate::spsc::{Atomic, MultiCore};
+#[cfg(feature = "async")]
+use crate::atomic_waker::AtomicWaker;
+use crate::{
+    binary_heap::{Deadline, MinBy, Stable},
+    cache_padded::CachePadded,
+    spsc::{Atomic, MultiCore},
+};
+
+/// `const-fn` version of [`broadcast::Queue`](../broadcast/struct.Queue.html)
+pub struct BroadcastQueue<A, U = usize, C = MultiCore> {
+    // this is where the writer writes the next item
+    pub(crate) tail: Atomic<U, C>,
+
+    pub(crate) buffer: UnsafeCell<MaybeUninit<A>>,
+}
 
 /// `const-fn` version of [`BinaryHeap`](../binary_heap/struct.BinaryHeap.html)
 pub struct BinaryHeap<A, K> {
@@ -10,22 +29,104 @@ pub struct BinaryHeap<A, K> {
     pub(crate) data: Vec<A>,
 }
 
+/// `const-fn` version of [`StableBinaryHeap`](../binary_heap/struct.StableBinaryHeap.html)
+pub struct StableBinaryHeap<A, K> {
+    pub(crate) heap: BinaryHeap<A, Stable<K>>,
+    pub(crate) seq: u32,
+}
+
+/// `const-fn` version of [`IndexedBinaryHeap`](../binary_heap/struct.IndexedBinaryHeap.html)
+pub struct IndexedBinaryHeap<A, S, K> {
+    pub(crate) _kind: PhantomData<K>,
+    // items in heap order, tagged with the slot that tracks their position
+    pub(crate) data: Vec<A>,
+    // slots that have been used at least once; the ones that are not in use are linked into the
+    // free list
+    pub(crate) slots: Vec<S>,
+    // head of the free slot list
+    pub(crate) free: usize,
+}
+
+/// `const-fn` version of [`TimerQueue`](../binary_heap/struct.TimerQueue.html)
+pub struct TimerQueue<A, S> {
+    pub(crate) heap: IndexedBinaryHeap<A, S, MinBy<Deadline>>,
+}
+
 /// `const-fn` version of [`LinearMap`](../struct.LinearMap.html)
 pub struct LinearMap<A> {
     pub(crate) buffer: Vec<A>,
 }
 
+/// `const-fn` version of [`SortedLinearMap`](../struct.SortedLinearMap.html)
+pub struct SortedLinearMap<A> {
+    pub(crate) buffer: Vec<A>,
+}
+
 /// `const-fn` version of [`spsc::Queue`](../spsc/struct.Queue.html)
 pub struct Queue<A, U = usize, C = MultiCore> {
     // this is from where we dequeue items
-    pub(crate) head: Atomic<U, C>,
+    pub(crate) head: CachePadded<Atomic<U, C>>,
 
     // this is where we enqueue new items
-    pub(crate) tail: Atomic<U, C>,
+    pub(crate) tail: CachePadded<Atomic<U, C>>,
+
+    // woken up when items are dequeued
+    #[cfg(feature = "async")]
+    pub(crate) producer_waker: AtomicWaker,
+
+    // woken up when items are enqueued
+    #[cfg(feature = "async")]
+    pub(crate) consumer_waker: AtomicWaker,
 
     pub(crate) buffer: MaybeUninit<A>,
 }
 
+/// A `static` variable that can be mutably borrowed only once; used by
+/// [`spsc_split!`](../macro.spsc_split.html)
+pub struct Singleton<T> {
+    taken: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> Singleton<T> {
+    /// `const` constructor
+    pub const fn new(value: T) -> Self {
+        Singleton {
+            taken: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Returns a mutable reference to the value the first time it's called; returns `None` on
+    /// subsequent calls
+    pub fn take(&'static self) -> Option<&'static mut T> {
+        if self.claim() {
+            Some(unsafe { &mut *self.value.get() })
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(no_cas))]
+    fn claim(&self) -> bool {
+        !self.taken.swap(true, Ordering::AcqRel)
+    }
+
+    #[cfg(no_cas)]
+    fn claim(&self) -> bool {
+        crate::critical_section::free(|| {
+            if self.taken.load(Ordering::Relaxed) {
+                false
+            } else {
+                self.taken.store(true, Ordering::Relaxed);
+                true
+            }
+        })
+    }
+}
+
+unsafe impl<T> Sync for Singleton<T> where T: Send {}
+
 /// `const-fn` version of [`String`](../struct.String.html)
 pub struct String<A> {
     pub(crate) vec: Vec<A>,
@@ -54,9 +54,14 @@
 //! - [`IndexSet`](struct.IndexSet.html) -- hash set
 //! - [`LinearMap`](struct.LinearMap.html)
 //! - [`Pool`](pool/struct.Pool.html) -- lock-free memory pool
+//! - [`SortedLinearMap`](struct.SortedLinearMap.html) -- map sorted by key
 //! - [`String`](struct.String.html)
+//! - [`TimerQueue`](binary_heap/struct.TimerQueue.html) -- deadline ordered timer queue
 //! - [`Vec`](struct.Vec.html)
-//! - [`mpmc::Q*`](mpmc/index.html) -- multiple producer multiple consumer lock-free queue
+//! - [`broadcast::Queue`](broadcast/struct.Queue.html) -- single producer multiple consumer
+//!   broadcast queue
+//! - [`mpmc::Queue`](mpmc/struct.Queue.html) -- multiple producer multiple consumer lock-free queue
+//! - [`mpsc::Queue`](mpsc/struct.Queue.html) -- multiple producer single consumer lock-free queue
 //! - [`spsc::Queue`](spsc/struct.Queue.html) -- single producer single consumer lock-free queue
 //!
 //! # Minimum Supported Rust Version (MSRV)
@@ -64,25 +69,34 @@
 //! This crate is guaranteed to compile on stable Rust 1.36 and up with its default set of features.
 //! It *might* compile on older versions but that may change in any new patch release.
 
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![deny(missing_docs)]
 #![deny(rust_2018_compatibility)]
 #![deny(rust_2018_idioms)]
 #![deny(warnings)]
 
+#[cfg(all(feature = "async", no_cas))]
+compile_error!("the `async` feature requires atomic compare-and-swap instructions");
+
 pub use binary_heap::BinaryHeap;
 pub use generic_array::typenum::consts;
 pub use generic_array::ArrayLength;
 pub use indexmap::{FnvIndexMap, IndexMap};
 pub use indexset::{FnvIndexSet, IndexSet};
 pub use linear_map::LinearMap;
+pub use sorted_linear_map::SortedLinearMap;
 pub use string::String;
 pub use vec::Vec;
 
+#[cfg(feature = "async")]
+mod atomic_waker;
+mod cache_padded;
 mod cfail;
+#[cfg(no_cas)]
+mod critical_section;
 mod indexmap;
 mod indexset;
-mod linear_map;
+mod sorted_linear_map;
 mod string;
 mod vec;
 
@@ -92,10 +106,11 @@ mod de;
 mod ser;
 
 pub mod binary_heap;
+pub mod broadcast;
 pub mod i;
-#[cfg(not(armv6m))]
+pub mod linear_map;
 pub mod mpmc;
-#[cfg(not(armv6m))]
+pub mod mpsc;
 pub mod pool;
 pub mod spsc;
 
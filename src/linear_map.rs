@@ -1,3 +1,7 @@
+//! A fixed capacity map that performs lookups via linear search
+//!
+//! See [`LinearMap`](struct.LinearMap.html) for more details
+
 use core::{borrow::Borrow, fmt, iter::FromIterator, mem, ops, ptr, slice};
 
 use generic_array::{ArrayLength, GenericArray};
@@ -96,10 +100,72 @@ where
     /// assert_eq!(map.contains_key(&1), true);
     /// assert_eq!(map.contains_key(&2), false);
     /// ```
-    pub fn contains_key(&self, key: &K) -> bool {
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
         self.get(key).is_some()
     }
 
+    /// Drains all the key-value pairs from the map, leaving it empty
+    ///
+    /// The pairs are yielded in arbitrary order. The map is left empty even if the iterator is
+    /// dropped before it's exhausted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::LinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: LinearMap<_, _, U8> = LinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
+    ///
+    /// let mut pairs = map.drain().collect::<Vec<_>>();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(1, "a"), (2, "b")]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        let pairs = self.0.buffer.as_mut_slice() as *mut [(K, V)];
+        // the map no longer owns the pairs; `Drain` drops the ones that it doesn't yield
+        self.0.buffer.len = 0;
+
+        Drain {
+            iter: unsafe { (*pairs).iter_mut() },
+        }
+    }
+
+    /// Gets the given key's corresponding entry in the map for in-place manipulation
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::LinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut letters: LinearMap<_, _, U16> = LinearMap::new();
+    ///
+    /// for ch in "a short treatise on fungi".chars().filter(|ch| *ch != ' ') {
+    ///     *letters.entry(ch).or_insert(0).unwrap() += 1;
+    /// }
+    ///
+    /// assert_eq!(letters[&'s'], 2);
+    /// assert_eq!(letters[&'t'], 3);
+    /// assert_eq!(letters.get(&'y'), None);
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, N> {
+        if let Some(index) = self.position(&key) {
+            Entry::Occupied(OccupiedEntry { map: self, index })
+        } else {
+            Entry::Vacant(VacantEntry { map: self, key })
+        }
+    }
+
     /// Returns a reference to the value corresponding to the key
     ///
     /// Computes in **O(N)** time
@@ -125,6 +191,29 @@ where
             .map(|(_, v)| v)
     }
 
+    /// Returns the key-value pair corresponding to the key
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::LinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: LinearMap<_, _, U8> = LinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.get_key_value(&1), Some((&1, &"a")));
+    /// assert_eq!(map.get_key_value(&2), None);
+    /// ```
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.iter().find(|&(k, _)| k.borrow() == key)
+    }
+
     /// Returns a mutable reference to the value corresponding to the key
     ///
     /// Computes in **O(N)** time
@@ -316,13 +405,73 @@ where
         K: Borrow<Q>,
         Q: Eq + ?Sized,
     {
-        let idx = self
-            .keys()
-            .enumerate()
-            .find(|&(_, k)| k.borrow() == key)
-            .map(|(idx, _)| idx);
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the key was previously in
+    /// the map
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::LinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: LinearMap<_, _, U8> = LinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.remove_entry(&1), Some((1, "a")));
+    /// assert_eq!(map.remove_entry(&1), None);
+    /// ```
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|idx| self.0.buffer.swap_remove(idx))
+    }
 
-        idx.map(|idx| self.0.buffer.swap_remove(idx).1)
+    /// Retains only the key-value pairs specified by the predicate
+    ///
+    /// In other words, removes all the pairs `(k, v)` for which `f(&k, &mut v)` returns `false`.
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::LinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: LinearMap<_, _, U8> = (0..8).map(|x| (x, x * 10)).collect();
+    /// map.retain(|&k, _| k % 2 == 0);
+    /// assert_eq!(map.len(), 4);
+    /// assert_eq!(map.get(&3), None);
+    /// assert_eq!(map[&4], 40);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let mut i = 0;
+        while i < self.len() {
+            let (k, v) = &mut self.0.buffer.as_mut_slice()[i];
+            if f(k, v) {
+                i += 1;
+            } else {
+                // the last pair is moved into slot `i` so don't advance
+                self.0.buffer.swap_remove(i);
+            }
+        }
+    }
+
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.keys().position(|k| k.borrow() == key)
     }
 
     /// An iterator visiting all values in arbitrary order
@@ -430,6 +579,35 @@ where
     }
 }
 
+impl<K, V, N> Extend<(K, V)> for LinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (k, v) in iterable {
+            self.insert(k, v).ok().unwrap();
+        }
+    }
+}
+
+impl<'a, K, V, N> Extend<(&'a K, &'a V)> for LinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq + Copy,
+    V: Copy,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        self.extend(iterable.into_iter().map(|(&key, &value)| (key, value)))
+    }
+}
+
 impl<K, V, N> FromIterator<(K, V)> for LinearMap<K, V, N>
 where
     N: ArrayLength<(K, V)>,
@@ -445,12 +623,13 @@ where
     }
 }
 
+/// An owning iterator over the key-value pairs of a [`LinearMap`](struct.LinearMap.html)
 pub struct IntoIter<K, V, N>
 where
     N: ArrayLength<(K, V)>,
     K: Eq,
 {
-    inner: <Vec<(K, V), N> as IntoIterator>::IntoIter,
+    pub(crate) inner: <Vec<(K, V), N> as IntoIterator>::IntoIter,
 }
 
 impl<K, V, N> Iterator for IntoIter<K, V, N>
@@ -496,8 +675,22 @@ where
     }
 }
 
+impl<'a, K, V, N> IntoIterator for &'a mut LinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the key-value pairs of a [`LinearMap`](struct.LinearMap.html)
 pub struct Iter<'a, K, V> {
-    iter: slice::Iter<'a, (K, V)>,
+    pub(crate) iter: slice::Iter<'a, (K, V)>,
 }
 
 impl<'a, K, V> Iterator for Iter<'a, K, V> {
@@ -508,6 +701,12 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
     }
 }
 
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|&(ref k, ref v)| (k, v))
+    }
+}
+
 impl<'a, K, V> Clone for Iter<'a, K, V> {
     fn clone(&self) -> Self {
         Self {
@@ -526,8 +725,10 @@ where
     }
 }
 
+/// An iterator over the key-value pairs of a [`LinearMap`](struct.LinearMap.html), with mutable
+/// references to the values
 pub struct IterMut<'a, K, V> {
-    iter: slice::IterMut<'a, (K, V)>,
+    pub(crate) iter: slice::IterMut<'a, (K, V)>,
 }
 
 impl<'a, K, V> Iterator for IterMut<'a, K, V> {
@@ -538,6 +739,213 @@ impl<'a, K, V> Iterator for IterMut<'a, K, V> {
     }
 }
 
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|&mut (ref k, ref mut v)| (k, v))
+    }
+}
+
+/// A draining iterator over the key-value pairs of a [`LinearMap`](struct.LinearMap.html)
+///
+/// This `struct` is created by the [`drain`](struct.LinearMap.html#method.drain) method
+pub struct Drain<'a, K, V> {
+    iter: slice::IterMut<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for Drain<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|pair| unsafe { ptr::read(pair) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, K, V> Drop for Drain<'a, K, V> {
+    fn drop(&mut self) {
+        for pair in &mut self.iter {
+            unsafe { ptr::drop_in_place(pair) }
+        }
+    }
+}
+
+/// A view into a single entry in a [`LinearMap`](struct.LinearMap.html), which may be either
+/// vacant or occupied
+///
+/// This `enum` is created by the [`entry`](struct.LinearMap.html#method.entry) method
+pub enum Entry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    /// An occupied entry
+    Occupied(OccupiedEntry<'a, K, V, N>),
+    /// A vacant entry
+    Vacant(VacantEntry<'a, K, V, N>),
+}
+
+impl<'a, K, V, N> Entry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    /// Returns a reference to this entry's key
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting `default` if empty, and returns a mutable
+    /// reference to the value in the entry
+    ///
+    /// Returns back `default` if the entry is vacant and the map is full
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, V> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and returns
+    /// a mutable reference to the value in the entry
+    ///
+    /// Returns back the computed value if the entry is vacant and the map is full
+    pub fn or_insert_with<F>(self, default: F) -> Result<&'a mut V, V>
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty, and returns a
+    /// mutable reference to the value in the entry
+    ///
+    /// Returns back the default value if the entry is vacant and the map is full
+    pub fn or_default(self) -> Result<&'a mut V, V>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts into
+    /// the map
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// A view into an occupied entry in a [`LinearMap`](struct.LinearMap.html)
+///
+/// It is part of the [`Entry`](enum.Entry.html) enum
+pub struct OccupiedEntry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    map: &'a mut LinearMap<K, V, N>,
+    index: usize,
+}
+
+impl<'a, K, V, N> OccupiedEntry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    /// Returns a reference to the key in the entry
+    pub fn key(&self) -> &K {
+        &self.pair().0
+    }
+
+    /// Returns a reference to the value in the entry
+    pub fn get(&self) -> &V {
+        &self.pair().1
+    }
+
+    /// Returns a mutable reference to the value in the entry
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.0.buffer.as_mut_slice()[self.index].1
+    }
+
+    /// Converts the entry into a mutable reference to its value, bound to the lifetime of the map
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.0.buffer.as_mut_slice()[self.index].1
+    }
+
+    /// Sets the value of the entry and returns the entry's old value
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry and returns it
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Takes the key-value pair out of the map and returns it
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.0.buffer.swap_remove(self.index)
+    }
+
+    fn pair(&self) -> &(K, V) {
+        &self.map.0.buffer.as_slice()[self.index]
+    }
+}
+
+/// A view into a vacant entry in a [`LinearMap`](struct.LinearMap.html)
+///
+/// It is part of the [`Entry`](enum.Entry.html) enum
+pub struct VacantEntry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    map: &'a mut LinearMap<K, V, N>,
+    key: K,
+}
+
+impl<'a, K, V, N> VacantEntry<'a, K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Eq,
+{
+    /// Returns a reference to the key that would be used when inserting a value through the entry
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Sets the value of the entry and returns a mutable reference to it
+    ///
+    /// Returns back the `value` if the map is full
+    ///
+    /// Computes in **O(1)** time
+    pub fn insert(self, value: V) -> Result<&'a mut V, V> {
+        let buffer = &mut self.map.0.buffer;
+        buffer.push((self.key, value)).map_err(|(_, v)| v)?;
+        let last = buffer.len - 1;
+        Ok(&mut buffer.as_mut_slice()[last].1)
+    }
+}
+
 impl<K, V, N, N2> PartialEq<LinearMap<K, V, N2>> for LinearMap<K, V, N>
 where
     K: Eq,
@@ -563,7 +971,9 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::{consts::*, LinearMap};
+    use std::{rc::Rc, vec::Vec};
+
+    use crate::{consts::*, linear_map::Entry, LinearMap};
 
     #[test]
     fn static_new() {
@@ -598,4 +1008,76 @@ mod test {
             assert!(a == b);
         }
     }
+
+    #[test]
+    fn entry() {
+        let mut map = LinearMap::<_, _, U2>::new();
+
+        *map.entry("a").or_insert(1).unwrap() += 10;
+        *map.entry("a").or_insert(1).unwrap() += 10;
+        assert_eq!(map["a"], 21);
+
+        map.entry("b").and_modify(|v| *v = 0).or_default().unwrap();
+        assert_eq!(map["b"], 0);
+
+        assert_eq!(map.entry("c").or_insert(3), Err(3));
+
+        match map.entry("a") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), ("a", 21)),
+            Entry::Vacant(_) => panic!(),
+        }
+
+        match map.entry("a") {
+            Entry::Occupied(_) => panic!(),
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), "a"),
+        }
+
+        if let Entry::Occupied(mut entry) = map.entry("b") {
+            assert_eq!(entry.insert(2), 0);
+            assert_eq!(entry.get(), &2);
+        }
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn retain() {
+        let mut map: LinearMap<_, _, U8> = (0..8).map(|x| (x, x)).collect();
+        map.retain(|k, v| {
+            *v *= 2;
+            k % 3 != 0
+        });
+
+        let mut pairs = map.into_iter().collect::<Vec<_>>();
+        pairs.sort();
+        assert_eq!(pairs, [(1, 2), (2, 4), (4, 8), (5, 10), (7, 14)]);
+    }
+
+    #[test]
+    fn drain() {
+        let rc = Rc::new(());
+        let mut map = LinearMap::<_, _, U4>::new();
+        for i in 0..4 {
+            map.insert(i, rc.clone()).unwrap();
+        }
+
+        // drop the `Drain` before it's exhausted
+        assert!(map.drain().next().is_some());
+        assert!(map.is_empty());
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        map.insert(0, rc.clone()).unwrap();
+        assert_eq!(map.drain().count(), 1);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn borrowed_keys() {
+        let mut map = LinearMap::<_, _, U2>::new();
+        map.insert(std::string::String::from("a"), 1).unwrap();
+
+        assert!(map.contains_key("a"));
+        assert_eq!(map.get_key_value("a"), Some((&"a".into(), &1)));
+        assert_eq!(map.remove_entry("a"), Some(("a".into(), 1)));
+        assert!(!map.contains_key("a"));
+    }
 }
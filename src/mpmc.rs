@@ -14,9 +14,9 @@
 //! use cortex_m::{asm, peripheral::syst::SystClkSource};
 //! use cortex_m_rt::{entry, exception};
 //! use cortex_m_semihosting::hprintln;
-//! use heapless::mpmc::Q2;
+//! use heapless::{consts::*, mpmc::Queue};
 //!
-//! static Q: Q2<u8> = Q2::new();
+//! static Q: Queue<u8, U8> = Queue::NEW;
 //!
 //! #[entry]
 //! fn main() -> ! {
@@ -71,493 +71,721 @@
 //!
 //! # Portability
 //!
-//! This module is not exposed to architectures that lack the instructions to implement CAS loops.
-//! Those architectures include ARMv6-M (`thumbv6m-none-eabi`) and MSP430 (`msp430-none-elf`).
+//! On architectures that lack the instructions to implement CAS loops, ARMv6-M
+//! (`thumbv6m-none-eabi`) and MSP430 (`msp430-none-elf`), the compare-and-swap operations are
+//! executed inside an interrupt-free critical section. The queue is *not* lock-free on those
+//! architectures but its API is the same.
 //!
 //! # References
 //!
-//! This is an implementation of Dmitry Vyukov's ["Bounded MPMC queue"][0] minus the cache padding.
+//! This is an implementation of Dmitry Vyukov's ["Bounded MPMC queue"][0]. The cache padding
+//! between the enqueue and dequeue positions, and between the sequence numbers of the cells, is
+//! only included when the `cache-padded` feature is enabled. With that feature every slot of the
+//! queue takes (at least) one cache line: 64 bytes, or 128 bytes on x86_64 and AArch64.
 //!
 //! [0]: http://www.1024cores.net/home/lock-free-algorithms/queues/bounded-mpmc-queue
 
 use core::{
     cell::UnsafeCell,
-    mem::MaybeUninit,
-    sync::atomic::{AtomicU8, Ordering},
+    cmp, fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr,
+    sync::atomic::Ordering,
 };
 
-/// MPMC queue with a capacity for 2 elements
-pub struct Q2<T> {
-    buffer: UnsafeCell<[Cell<T>; 2]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
+use generic_array::typenum::consts::*;
+
+use crate::{
+    cache_padded::CachePadded,
+    sealed::mpmc::{Capacity, Cell, Uxx},
+};
+
+/// MPMC queue with a capacity for `N` elements
+///
+/// `N` must be a power of two greater than one. The width of the enqueue and dequeue positions
+/// depends on the capacity: `u8` up to `U32`, `u16` up to `U8192` and `usize` above that.
+///
+/// # Examples
+///
+/// ```
+/// use heapless::{consts::*, mpmc::Queue};
+///
+/// static Q: Queue<u32, U512> = Queue::NEW;
+///
+/// Q.enqueue(0).unwrap();
+/// Q.enqueue(1).unwrap();
+///
+/// assert_eq!(Q.dequeue(), Some(0));
+/// assert_eq!(Q.dequeue(), Some(1));
+/// assert_eq!(Q.dequeue(), None);
+/// ```
+pub struct Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    buffer: UnsafeCell<N::Cells>,
+    dequeue_pos: CachePadded<UnsafeCell<N::Index>>,
+    enqueue_pos: CachePadded<UnsafeCell<N::Index>>,
 }
 
-impl<T> Q2<T> {
-    const MASK: u8 = 2 - 1;
+impl<T, N> Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    /// An empty queue
+    ///
+    /// Use this constant to initialize a queue in a `static` variable
+    pub const NEW: Self = Queue {
+        buffer: UnsafeCell::new(N::CELLS),
+        dequeue_pos: CachePadded::new(UnsafeCell::new(<N::Index as Uxx>::ZERO)),
+        enqueue_pos: CachePadded::new(UnsafeCell::new(<N::Index as Uxx>::ZERO)),
+    };
 
     /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([Cell::new(0), Cell::new(1)]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
-        }
+    pub fn new() -> Self {
+        Self::NEW
     }
 
-    /// Returns the item in the front of the queue, or `None` if the queue is empty
-    pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+    /// Returns the maximum number of elements the queue can hold
+    pub fn capacity(&self) -> usize {
+        N::to_usize()
     }
 
-    /// Adds an `item` to the end of the queue
+    /// Returns the number of elements in the queue
     ///
-    /// Returns back the `item` if the queue is full
-    pub fn enqueue(&self, item: T) -> Result<(), T> {
+    /// The result is only approximate if other contexts are operating on the queue at the same
+    /// time. Slots that have been reserved but not yet committed are counted as elements.
+    pub fn len(&self) -> usize {
         unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
+            // NOTE these loads are not ordered with respect to each other so either position may be
+            // stale and the difference may even wrap around; the clamp keeps the result in range
+            let dequeue_pos: N::Index = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+            let enqueue_pos: N::Index = Uxx::load(self.enqueue_pos.get(), Ordering::Relaxed);
+
+            cmp::min(
+                enqueue_pos.wrapping_sub(dequeue_pos).to_usize() / 2,
+                N::to_usize(),
             )
         }
     }
-}
-
-unsafe impl<T> Sync for Q2<T> where T: Send {}
 
-/// MPMC queue with a capacity for 4 elements
-pub struct Q4<T> {
-    buffer: UnsafeCell<[Cell<T>; 4]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
-}
-
-impl<T> Q4<T> {
-    const MASK: u8 = 4 - 1;
-
-    /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([Cell::new(0), Cell::new(1), Cell::new(2), Cell::new(3)]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
-        }
+    /// Returns `true` if the queue has a length of 0
+    ///
+    /// See [`len`](struct.Queue.html#method.len) for caveats
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     /// Returns the item in the front of the queue, or `None` if the queue is empty
     pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+        unsafe {
+            let mut pos = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+
+            loop {
+                let (cell, index) = self.cell(pos);
+                let (seq, skip) = sequence(cell, index);
+                let dif = Uxx::dif(seq, pos.wrapping_add(Uxx::TWO));
+
+                if dif == 0 {
+                    if Uxx::compare_exchange_weak(
+                        self.dequeue_pos.get(),
+                        pos,
+                        pos.wrapping_add(Uxx::TWO),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                    {
+                        if let Some(item) = self.release(cell, pos, index, skip) {
+                            return Some(item);
+                        }
+
+                        // the cell was skipped; move on to the next one
+                        pos = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+                    }
+                } else if dif < 0 {
+                    return None;
+                } else {
+                    pos = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+                }
+            }
+        }
     }
 
     /// Adds an `item` to the end of the queue
     ///
     /// Returns back the `item` if the queue is full
     pub fn enqueue(&self, item: T) -> Result<(), T> {
+        match self.reserve() {
+            Some(mut reservation) => unsafe {
+                reservation.slot().as_mut_ptr().write(item);
+                reservation.commit();
+                Ok(())
+            },
+            None => Err(item),
+        }
+    }
+
+    /// Reserves the slot at the end of the queue so that an item can be constructed in place
+    ///
+    /// Returns `None` if the queue is full. See [`Reservation`](struct.Reservation.html) for
+    /// details.
+    pub fn reserve(&self) -> Option<Reservation<'_, T, N>> {
         unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
-            )
+            let mut pos = Uxx::load(self.enqueue_pos.get(), Ordering::Relaxed);
+
+            loop {
+                let (cell, index) = self.cell(pos);
+                let (seq, _) = sequence(cell, index);
+                let dif = Uxx::dif(seq, pos);
+
+                if dif == 0 {
+                    if Uxx::compare_exchange_weak(
+                        self.enqueue_pos.get(),
+                        pos,
+                        pos.wrapping_add(Uxx::TWO),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                    {
+                        return Some(Reservation {
+                            cell,
+                            pos,
+                            index,
+                            _queue: PhantomData,
+                        });
+                    }
+                } else if dif < 0 {
+                    return None;
+                } else {
+                    pos = Uxx::load(self.enqueue_pos.get(), Ordering::Relaxed);
+                }
+            }
         }
     }
-}
 
-unsafe impl<T> Sync for Q4<T> where T: Send {}
+    /// Constructs an item in place at the end of the queue
+    ///
+    /// Returns back `f` if the queue is full. If `f` panics the slot is skipped by the consumers.
+    ///
+    /// # Unsafety
+    ///
+    /// `f` must initialize the slot it's given
+    pub unsafe fn enqueue_with<F>(&self, f: F) -> Result<(), F>
+    where
+        F: FnOnce(&mut MaybeUninit<T>),
+    {
+        match self.reserve() {
+            Some(mut reservation) => {
+                f(reservation.slot());
+                reservation.commit();
+                Ok(())
+            }
+            None => Err(f),
+        }
+    }
 
-/// MPMC queue with a capacity for 8 elements
-pub struct Q8<T> {
-    buffer: UnsafeCell<[Cell<T>; 8]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
-}
+    // Dequeues an item without a CAS loop
+    //
+    // Used by `mpsc::Consumer`. The caller must ensure that no other context dequeues from this
+    // queue at the same time
+    pub(crate) unsafe fn dequeue_exclusive(&self) -> Option<T> {
+        loop {
+            let pos: N::Index = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+            let (cell, index) = self.cell(pos);
 
-impl<T> Q8<T> {
-    const MASK: u8 = 8 - 1;
+            let (seq, skip) = sequence(cell, index);
 
-    /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([
-                Cell::new(0),
-                Cell::new(1),
-                Cell::new(2),
-                Cell::new(3),
-                Cell::new(4),
-                Cell::new(5),
-                Cell::new(6),
-                Cell::new(7),
-            ]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
+            // NOTE the cell is either empty or reserved by a producer that hasn't committed it yet
+            if seq != pos.wrapping_add(Uxx::TWO) {
+                return None;
+            }
+
+            Uxx::store(
+                self.dequeue_pos.get(),
+                pos.wrapping_add(Uxx::TWO),
+                Ordering::Relaxed,
+            );
+
+            if let Some(item) = self.release(cell, pos, index, skip) {
+                return Some(item);
+            }
         }
     }
 
-    /// Returns the item in the front of the queue, or `None` if the queue is empty
-    pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+    // Takes the item out of the `cell` that was claimed at `pos` and hands the cell back to the
+    // producers; returns `None` if the cell was skipped
+    unsafe fn release(
+        &self,
+        cell: *mut Cell<T, N::Index>,
+        pos: N::Index,
+        index: N::Index,
+        skip: bool,
+    ) -> Option<T> {
+        let data = if skip {
+            None
+        } else {
+            Some((*cell).data.as_ptr().read())
+        };
+
+        // NOTE this also clears the skip bit
+        let cap = <N::Index as Uxx>::truncate(2 * N::to_usize());
+        Uxx::store(
+            (*cell).sequence.get(),
+            pos.wrapping_add(cap).wrapping_sub(index),
+            Ordering::Release,
+        );
+        data
     }
 
-    /// Adds an `item` to the end of the queue
-    ///
-    /// Returns back the `item` if the queue is full
-    pub fn enqueue(&self, item: T) -> Result<(), T> {
+    // Returns the cell that `pos` maps to and the position of that cell in the first lap
+    unsafe fn cell(&self, pos: N::Index) -> (*mut Cell<T, N::Index>, N::Index) {
+        let index = (pos.to_usize() / 2) & (N::to_usize() - 1);
+        let cell = (self.buffer.get() as *mut Cell<T, N::Index>).add(index);
+        (cell, Uxx::truncate(2 * index))
+    }
+}
+
+unsafe impl<T, N> Sync for Queue<T, N>
+where
+    T: Send,
+    N: Capacity<T>,
+{
+}
+
+impl<T, N> Drop for Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    fn drop(&mut self) {
         unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
-            )
+            let mut pos: N::Index = Uxx::load(self.dequeue_pos.get(), Ordering::Relaxed);
+            let enqueue_pos: N::Index = Uxx::load(self.enqueue_pos.get(), Ordering::Relaxed);
+
+            while pos != enqueue_pos {
+                let (cell, index) = self.cell(pos);
+
+                // NOTE reserved slots that were never published, and skipped slots, don't hold an
+                // item
+                if sequence(cell, index) == (pos.wrapping_add(Uxx::TWO), false) {
+                    ptr::drop_in_place((*cell).data.as_mut_ptr());
+                }
+
+                pos = pos.wrapping_add(Uxx::TWO);
+            }
         }
     }
 }
 
-unsafe impl<T> Sync for Q8<T> where T: Send {}
+// NOTE the items can't be printed because other contexts may be dequeuing them at the same time
+impl<T, N> fmt::Debug for Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+// Loads the (logical) sequence number of the `cell` at `index`, and whether the cell was skipped
+unsafe fn sequence<T, I>(cell: *const Cell<T, I>, index: I) -> (I, bool)
+where
+    I: Uxx,
+{
+    let seq = I::load((*cell).sequence.get(), Ordering::Acquire).to_usize();
+    (I::truncate(seq & !1).wrapping_add(index), seq & 1 == 1)
+}
+
+macro_rules! q {
+    ($($Q:ident, $N:ident, $doc:expr;)+) => {
+        $(
+            #[doc = $doc]
+            ///
+            /// This is a thin wrapper around [`Queue`](struct.Queue.html) that can be created with
+            /// a `const fn`.
+            pub struct $Q<T>(Queue<T, $N>);
+
+            impl<T> $Q<T> {
+                /// Creates an empty queue
+                pub const fn new() -> Self {
+                    $Q(Queue::NEW)
+                }
+
+                /// Returns the item in the front of the queue, or `None` if the queue is empty
+                pub fn dequeue(&self) -> Option<T> {
+                    self.0.dequeue()
+                }
+
+                /// Adds an `item` to the end of the queue
+                ///
+                /// Returns back the `item` if the queue is full
+                pub fn enqueue(&self, item: T) -> Result<(), T> {
+                    self.0.enqueue(item)
+                }
+
+                /// Reserves the slot at the end of the queue so that an item can be constructed in
+                /// place
+                ///
+                /// Returns `None` if the queue is full. See
+                /// [`Reservation`](struct.Reservation.html) for details.
+                pub fn reserve(&self) -> Option<Reservation<'_, T, $N>> {
+                    self.0.reserve()
+                }
+
+                /// Constructs an item in place at the end of the queue
+                ///
+                /// Returns back `f` if the queue is full
+                ///
+                /// # Unsafety
+                ///
+                /// `f` must initialize the slot it's given
+                pub unsafe fn enqueue_with<F>(&self, f: F) -> Result<(), F>
+                where
+                    F: FnOnce(&mut MaybeUninit<T>),
+                {
+                    self.0.enqueue_with(f)
+                }
+
+                /// Returns the maximum number of elements the queue can hold
+                pub fn capacity(&self) -> usize {
+                    self.0.capacity()
+                }
+
+                /// Returns the number of elements in the queue
+                ///
+                /// See [`Queue::len`](struct.Queue.html#method.len) for caveats
+                pub fn len(&self) -> usize {
+                    self.0.len()
+                }
+
+                /// Returns `true` if the queue has a length of 0
+                ///
+                /// See [`Queue::len`](struct.Queue.html#method.len) for caveats
+                pub fn is_empty(&self) -> bool {
+                    self.0.is_empty()
+                }
+            }
 
-/// MPMC queue with a capacity for 16 elements
-pub struct Q16<T> {
-    buffer: UnsafeCell<[Cell<T>; 16]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
+            impl<T> fmt::Debug for $Q<T> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.debug_struct(stringify!($Q))
+                        .field("len", &self.len())
+                        .field("capacity", &self.capacity())
+                        .finish()
+                }
+            }
+        )+
+    };
 }
 
-impl<T> Q16<T> {
-    const MASK: u8 = 16 - 1;
+q! {
+    Q2, U2, "MPMC queue with a capacity for 2 elements";
+    Q4, U4, "MPMC queue with a capacity for 4 elements";
+    Q8, U8, "MPMC queue with a capacity for 8 elements";
+    Q16, U16, "MPMC queue with a capacity for 16 elements";
+    Q32, U32, "MPMC queue with a capacity for 32 elements";
+    Q64, U64, "MPMC queue with a capacity for 64 elements";
+}
 
-    /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([
-                Cell::new(0),
-                Cell::new(1),
-                Cell::new(2),
-                Cell::new(3),
-                Cell::new(4),
-                Cell::new(5),
-                Cell::new(6),
-                Cell::new(7),
-                Cell::new(8),
-                Cell::new(9),
-                Cell::new(10),
-                Cell::new(11),
-                Cell::new(12),
-                Cell::new(13),
-                Cell::new(14),
-                Cell::new(15),
-            ]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
-        }
-    }
+/// A reserved slot at the end of an MPMC queue
+///
+/// The item becomes available to consumers when the reservation is committed. Until then the
+/// consumers can't get past this slot so the item should be constructed and committed promptly.
+/// Dropping the reservation without committing it (e.g. because the code constructing the item
+/// panicked) publishes the slot as empty; consumers skip it.
+pub struct Reservation<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    cell: *mut Cell<T, N::Index>,
+    pos: N::Index,
+    index: N::Index,
+    _queue: PhantomData<&'a ()>,
+}
 
-    /// Returns the item in the front of the queue, or `None` if the queue is empty
-    pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+impl<'a, T, N> Reservation<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    /// Returns the slot where the item must be constructed
+    pub fn slot(&mut self) -> &mut MaybeUninit<T> {
+        unsafe { &mut (*self.cell).data }
     }
 
-    /// Adds an `item` to the end of the queue
+    /// Adds the item in the slot to the queue
     ///
-    /// Returns back the `item` if the queue is full
-    pub fn enqueue(&self, item: T) -> Result<(), T> {
-        unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
-            )
+    /// # Unsafety
+    ///
+    /// The slot must have been initialized
+    pub unsafe fn commit(self) {
+        self.publish(false);
+        mem::forget(self);
+    }
+
+    unsafe fn publish(&self, skip: bool) {
+        let mut seq = self.pos.wrapping_add(Uxx::TWO).wrapping_sub(self.index);
+        if skip {
+            seq = seq.wrapping_add(Uxx::ONE);
         }
+        Uxx::store((*self.cell).sequence.get(), seq, Ordering::Release);
     }
 }
 
-unsafe impl<T> Sync for Q16<T> where T: Send {}
-
-/// MPMC queue with a capacity for 32 elements
-pub struct Q32<T> {
-    buffer: UnsafeCell<[Cell<T>; 32]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
+impl<'a, T, N> Drop for Reservation<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    fn drop(&mut self) {
+        // NOTE the cell is published with the skip bit set
+        unsafe { self.publish(true) }
+    }
 }
 
-impl<T> Q32<T> {
-    const MASK: u8 = 32 - 1;
+#[cfg(test)]
+mod tests {
+    use crate::consts::*;
 
-    /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([
-                Cell::new(0),
-                Cell::new(1),
-                Cell::new(2),
-                Cell::new(3),
-                Cell::new(4),
-                Cell::new(5),
-                Cell::new(6),
-                Cell::new(7),
-                Cell::new(8),
-                Cell::new(9),
-                Cell::new(10),
-                Cell::new(11),
-                Cell::new(12),
-                Cell::new(13),
-                Cell::new(14),
-                Cell::new(15),
-                Cell::new(16),
-                Cell::new(17),
-                Cell::new(18),
-                Cell::new(19),
-                Cell::new(20),
-                Cell::new(21),
-                Cell::new(22),
-                Cell::new(23),
-                Cell::new(24),
-                Cell::new(25),
-                Cell::new(26),
-                Cell::new(27),
-                Cell::new(28),
-                Cell::new(29),
-                Cell::new(30),
-                Cell::new(31),
-            ]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
-        }
-    }
+    use super::{Queue, Q2, Q32, Q4, Q8};
 
-    /// Returns the item in the front of the queue, or `None` if the queue is empty
-    pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+    #[test]
+    fn sanity() {
+        let q = Q2::new();
+        q.enqueue(0).unwrap();
+        q.enqueue(1).unwrap();
+        assert!(q.enqueue(2).is_err());
+
+        assert_eq!(q.dequeue(), Some(0));
+        assert_eq!(q.dequeue(), Some(1));
+        assert_eq!(q.dequeue(), None);
     }
 
-    /// Adds an `item` to the end of the queue
-    ///
-    /// Returns back the `item` if the queue is full
-    pub fn enqueue(&self, item: T) -> Result<(), T> {
+    #[test]
+    fn in_place() {
+        let q: Q2<[u8; 1024]> = Q2::new();
+
+        let mut reservation = q.reserve().unwrap();
+        unsafe { reservation.slot().as_mut_ptr().write([1; 1024]) };
+
+        // the reserved item is not visible until committed
+        assert_eq!(q.dequeue(), None);
+        unsafe { reservation.commit() }
+
         unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
-            )
-        }
+            q.enqueue_with(|slot| slot.as_mut_ptr().write([2; 1024]))
+                .ok()
+                .unwrap()
+        };
+        assert!(q.reserve().is_none());
+        assert!(unsafe { q.enqueue_with(|_| unreachable!()) }.is_err());
+
+        assert_eq!(q.dequeue().map(|frame| frame[1023]), Some(1));
+        assert_eq!(q.dequeue().map(|frame| frame[1023]), Some(2));
     }
-}
 
-unsafe impl<T> Sync for Q32<T> where T: Send {}
+    #[test]
+    fn dropped_reservation() {
+        let q = Q4::new();
 
-/// MPMC queue with a capacity for 64 elements
-pub struct Q64<T> {
-    buffer: UnsafeCell<[Cell<T>; 64]>,
-    dequeue_pos: AtomicU8,
-    enqueue_pos: AtomicU8,
-}
+        q.enqueue(0).unwrap();
+        core::mem::drop(q.reserve().unwrap());
+        q.enqueue(2).unwrap();
 
-impl<T> Q64<T> {
-    const MASK: u8 = 64 - 1;
+        // consumers skip the slot of the reservation that was not committed
+        assert_eq!(q.dequeue(), Some(0));
+        assert_eq!(q.dequeue(), Some(2));
+        assert_eq!(q.dequeue(), None);
 
-    /// Creates an empty queue
-    pub const fn new() -> Self {
-        Self {
-            buffer: UnsafeCell::new([
-                Cell::new(0),
-                Cell::new(1),
-                Cell::new(2),
-                Cell::new(3),
-                Cell::new(4),
-                Cell::new(5),
-                Cell::new(6),
-                Cell::new(7),
-                Cell::new(8),
-                Cell::new(9),
-                Cell::new(10),
-                Cell::new(11),
-                Cell::new(12),
-                Cell::new(13),
-                Cell::new(14),
-                Cell::new(15),
-                Cell::new(16),
-                Cell::new(17),
-                Cell::new(18),
-                Cell::new(19),
-                Cell::new(20),
-                Cell::new(21),
-                Cell::new(22),
-                Cell::new(23),
-                Cell::new(24),
-                Cell::new(25),
-                Cell::new(26),
-                Cell::new(27),
-                Cell::new(28),
-                Cell::new(29),
-                Cell::new(30),
-                Cell::new(31),
-                Cell::new(32),
-                Cell::new(33),
-                Cell::new(34),
-                Cell::new(35),
-                Cell::new(36),
-                Cell::new(37),
-                Cell::new(38),
-                Cell::new(39),
-                Cell::new(40),
-                Cell::new(41),
-                Cell::new(42),
-                Cell::new(43),
-                Cell::new(44),
-                Cell::new(45),
-                Cell::new(46),
-                Cell::new(47),
-                Cell::new(48),
-                Cell::new(49),
-                Cell::new(50),
-                Cell::new(51),
-                Cell::new(52),
-                Cell::new(53),
-                Cell::new(54),
-                Cell::new(55),
-                Cell::new(56),
-                Cell::new(57),
-                Cell::new(58),
-                Cell::new(59),
-                Cell::new(60),
-                Cell::new(61),
-                Cell::new(62),
-                Cell::new(63),
-            ]),
-            dequeue_pos: AtomicU8::new(0),
-            enqueue_pos: AtomicU8::new(0),
+        // and the slot can be used again
+        for i in 0..4 {
+            q.enqueue(i).unwrap();
+        }
+        for i in 0..4 {
+            assert_eq!(q.dequeue(), Some(i));
         }
     }
 
-    /// Returns the item in the front of the queue, or `None` if the queue is empty
-    pub fn dequeue(&self) -> Option<T> {
-        unsafe { dequeue(self.buffer.get() as *mut _, &self.dequeue_pos, Self::MASK) }
+    #[test]
+    fn enqueue_with_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let q: Queue<std::string::String, U2> = Queue::new();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+            q.enqueue_with(|_| panic!()).ok();
+        }));
+        assert!(res.is_err());
+
+        q.enqueue("a".into()).unwrap();
+        assert_eq!(q.dequeue().as_ref().map(|s| &s[..]), Some("a"));
+        assert_eq!(q.dequeue(), None);
+
+        // the skipped slot doesn't hold an item that needs to be dropped
+        core::mem::drop(q.reserve());
+        q.enqueue("b".into()).unwrap();
     }
 
-    /// Adds an `item` to the end of the queue
-    ///
-    /// Returns back the `item` if the queue is full
-    pub fn enqueue(&self, item: T) -> Result<(), T> {
-        unsafe {
-            enqueue(
-                self.buffer.get() as *mut _,
-                &self.enqueue_pos,
-                Self::MASK,
-                item,
-            )
+    #[test]
+    fn full_after_wrap_around() {
+        let q: Q32<u8> = Q32::new();
+
+        // move the positions past the point where the `u8`s wrap around
+        for i in 0..300 {
+            q.enqueue(i as u8).unwrap();
+            assert_eq!(q.dequeue(), Some(i as u8));
+        }
+
+        for i in 0..32 {
+            q.enqueue(i).unwrap();
+        }
+        assert_eq!(q.enqueue(32), Err(32));
+
+        for i in 0..32 {
+            assert_eq!(q.dequeue(), Some(i));
         }
+        assert_eq!(q.dequeue(), None);
     }
-}
 
-unsafe impl<T> Sync for Q64<T> where T: Send {}
+    #[test]
+    fn u16_positions() {
+        static Q: Queue<u16, U512> = Queue::NEW;
 
-struct Cell<T> {
-    data: MaybeUninit<T>,
-    sequence: AtomicU8,
-}
+        for _ in 0..200 {
+            for i in 0..512 {
+                Q.enqueue(i).unwrap();
+            }
+            assert!(Q.enqueue(512).is_err());
 
-impl<T> Cell<T> {
-    const fn new(seq: u8) -> Self {
-        Self {
-            data: MaybeUninit::uninit(),
-            sequence: AtomicU8::new(seq),
+            for i in 0..512 {
+                assert_eq!(Q.dequeue(), Some(i));
+            }
+            assert_eq!(Q.dequeue(), None);
         }
     }
-}
 
-unsafe fn dequeue<T>(buffer: *mut Cell<T>, dequeue_pos: &AtomicU8, mask: u8) -> Option<T> {
-    let mut pos = dequeue_pos.load(Ordering::Relaxed);
-
-    let mut cell;
-    loop {
-        cell = buffer.add(usize::from(pos & mask));
-        let seq = (*cell).sequence.load(Ordering::Acquire);
-        let dif = i16::from(seq) - i16::from(pos.wrapping_add(1));
-
-        if dif == 0 {
-            if dequeue_pos
-                .compare_exchange_weak(
-                    pos,
-                    pos.wrapping_add(1),
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                )
-                .is_ok()
-            {
-                break;
-            }
-        } else if dif < 0 {
-            return None;
-        } else {
-            pos = dequeue_pos.load(Ordering::Relaxed);
+    #[test]
+    fn usize_positions() {
+        static Q: Queue<u8, U32768> = Queue::NEW;
+
+        for i in 0..32768 {
+            Q.enqueue(i as u8).unwrap();
         }
+        assert!(Q.enqueue(0).is_err());
+
+        for i in 0..32768 {
+            assert_eq!(Q.dequeue(), Some(i as u8));
+        }
+        assert_eq!(Q.dequeue(), None);
     }
 
-    let data = (*cell).data.as_ptr().read();
-    (*cell)
-        .sequence
-        .store(pos.wrapping_add(mask).wrapping_add(1), Ordering::Release);
-    Some(data)
-}
+    #[test]
+    fn len() {
+        let q: Q4<u8> = Q4::new();
+        assert_eq!(q.capacity(), 4);
+        assert!(q.is_empty());
+
+        for i in 0..300 {
+            q.enqueue(i as u8).unwrap();
+            q.enqueue(i as u8).unwrap();
+            assert_eq!(q.len(), 2);
+
+            q.dequeue().unwrap();
+            q.dequeue().unwrap();
+            assert!(q.is_empty());
+        }
+
+        let mut reservation = q.reserve().unwrap();
+        assert_eq!(q.len(), 1);
+        unsafe {
+            reservation.slot().as_mut_ptr().write(0);
+            reservation.commit();
+        }
+        assert_eq!(q.len(), 1);
+    }
 
-unsafe fn enqueue<T>(
-    buffer: *mut Cell<T>,
-    enqueue_pos: &AtomicU8,
-    mask: u8,
-    item: T,
-) -> Result<(), T> {
-    let mut pos = enqueue_pos.load(Ordering::Relaxed);
-
-    let mut cell;
-    loop {
-        cell = buffer.add(usize::from(pos & mask));
-        let seq = (*cell).sequence.load(Ordering::Acquire);
-        let dif = i16::from(seq) - i16::from(pos);
-
-        if dif == 0 {
-            if enqueue_pos
-                .compare_exchange_weak(
-                    pos,
-                    pos.wrapping_add(1),
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                )
-                .is_ok()
-            {
-                break;
+    #[test]
+    fn drop() {
+        struct Droppable;
+        impl Droppable {
+            fn new() -> Self {
+                unsafe {
+                    COUNT += 1;
+                }
+                Droppable
             }
-        } else if dif < 0 {
-            return Err(item);
-        } else {
-            pos = enqueue_pos.load(Ordering::Relaxed);
         }
+
+        impl Drop for Droppable {
+            fn drop(&mut self) {
+                unsafe {
+                    COUNT -= 1;
+                }
+            }
+        }
+
+        static mut COUNT: i32 = 0;
+
+        {
+            let q: Queue<Droppable, U4> = Queue::new();
+            q.enqueue(Droppable::new()).ok().unwrap();
+            q.enqueue(Droppable::new()).ok().unwrap();
+            q.enqueue(Droppable::new()).ok().unwrap();
+            q.dequeue().unwrap();
+        }
+
+        assert_eq!(unsafe { COUNT }, 0);
+
+        {
+            let q: Q8<Droppable> = Q8::new();
+            for _ in 0..10 {
+                q.enqueue(Droppable::new()).ok().unwrap();
+                q.dequeue().unwrap();
+            }
+            q.enqueue(Droppable::new()).ok().unwrap();
+
+            // a reservation that's never committed holds no item
+            q.reserve().unwrap();
+            q.enqueue(Droppable::new()).ok().unwrap();
+        }
+
+        assert_eq!(unsafe { COUNT }, 0);
     }
 
-    (*cell).data.as_mut_ptr().write(item);
-    (*cell)
-        .sequence
-        .store(pos.wrapping_add(1), Ordering::Release);
-    Ok(())
-}
+    #[test]
+    fn debug() {
+        let q: Q2<u8> = Q2::new();
+        q.enqueue(0).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::Q2;
+        assert_eq!(format!("{:?}", q), "Q2 { len: 1, capacity: 2 }");
+    }
 
     #[test]
-    fn sanity() {
-        let q = Q2::new();
+    fn new() {
+        let q: Queue<i32, U2> = Queue::new();
+
         q.enqueue(0).unwrap();
         q.enqueue(1).unwrap();
-        assert!(q.enqueue(2).is_err());
-
+        assert_eq!(q.enqueue(2), Err(2));
         assert_eq!(q.dequeue(), Some(0));
-        assert_eq!(q.dequeue(), Some(1));
-        assert_eq!(q.dequeue(), None);
+    }
+
+    #[test]
+    fn cell_layout() {
+        let q: Queue<u8, U2> = Queue::new();
+        let (c0, _) = unsafe { q.cell(0) };
+        let (c1, _) = unsafe { q.cell(2) };
+        let stride = c1 as usize - c0 as usize;
+
+        if cfg!(feature = "cache-padded") {
+            assert!(stride >= 64);
+        } else {
+            // the skip flag is encoded in the sequence number; the cell is just the item and the
+            // sequence number
+            assert_eq!(stride, 2);
+        }
     }
 }
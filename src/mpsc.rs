@@ -0,0 +1,355 @@
+//! A fixed capacity Multiple-Producer Single-Consumer (MPSC) lock-free queue
+//!
+//! Any number of contexts can enqueue items using a shared reference to the queue but only the
+//! context that holds the queue's [`Consumer`](struct.Consumer.html) can dequeue them. As there's
+//! a single consumer, dequeuing an item doesn't need a CAS loop.
+//!
+//! # Example
+//!
+//! This queue can be constructed in "const context". Placing it in a `static` variable lets *all*
+//! contexts (interrupts / threads / `main`) safely enqueue items into it.
+//!
+//! ``` ignore
+//! #![no_main]
+//! #![no_std]
+//!
+//! use panic_semihosting as _;
+//!
+//! use cortex_m::{asm, peripheral::syst::SystClkSource};
+//! use cortex_m_rt::{entry, exception};
+//! use cortex_m_semihosting::hprintln;
+//! use heapless::{consts::*, mpsc::Queue};
+//!
+//! static Q: Queue<u8, U8> = Queue::NEW;
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     if let Some(p) = cortex_m::Peripherals::take() {
+//!         let mut syst = p.SYST;
+//!
+//!         // configures the system timer to trigger a SysTick exception every second
+//!         syst.set_clock_source(SystClkSource::Core);
+//!         syst.set_reload(12_000_000);
+//!         syst.enable_counter();
+//!         syst.enable_interrupt();
+//!     }
+//!
+//!     let mut consumer = Q.consumer().unwrap();
+//!
+//!     loop {
+//!         if let Some(x) = consumer.dequeue() {
+//!             hprintln!("{}", x).ok();
+//!         } else {
+//!             asm::wfi();
+//!         }
+//!     }
+//! }
+//!
+//! #[exception]
+//! fn SysTick() {
+//!     static mut COUNT: u8 = 0;
+//!
+//!     Q.enqueue(*COUNT).ok();
+//!     *COUNT += 1;
+//! }
+//! ```
+//!
+//! # Portability
+//!
+//! See the ['Portability'](../mpmc/index.html#portability) section of the `mpmc` module. The
+//! enqueue operation is the same one the `mpmc` queues use.
+
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{mpmc, sealed::mpmc::Capacity};
+
+pub use crate::mpmc::Reservation;
+
+/// MPSC queue with a capacity for `N` elements
+///
+/// `N` must be a power of two greater than one.
+///
+/// # Examples
+///
+/// ```
+/// use heapless::{consts::*, mpsc::Queue};
+///
+/// static Q: Queue<u32, U512> = Queue::NEW;
+///
+/// // there's a single consumer
+/// let mut consumer = Q.consumer().unwrap();
+/// assert!(Q.consumer().is_none());
+///
+/// // but there can be many producers
+/// Q.enqueue(0).unwrap();
+/// Q.enqueue(1).unwrap();
+///
+/// assert_eq!(consumer.dequeue(), Some(0));
+/// assert_eq!(consumer.dequeue(), Some(1));
+/// assert_eq!(consumer.dequeue(), None);
+/// ```
+pub struct Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    queue: mpmc::Queue<T, N>,
+    consumer_taken: AtomicBool,
+}
+
+impl<T, N> Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    /// An empty queue
+    ///
+    /// Use this constant to initialize a queue in a `static` variable
+    pub const NEW: Self = Queue {
+        queue: mpmc::Queue::NEW,
+        consumer_taken: AtomicBool::new(false),
+    };
+
+    /// Creates an empty queue
+    pub fn new() -> Self {
+        Self::NEW
+    }
+
+    /// Returns the consumer end point of the queue
+    ///
+    /// Returns `None` if the consumer is currently held by some other context. The consumer can
+    /// be claimed again once it has been dropped.
+    pub fn consumer(&self) -> Option<Consumer<'_, T, N>> {
+        if self.take_consumer() {
+            Some(Consumer { queue: self })
+        } else {
+            None
+        }
+    }
+
+    /// Adds an `item` to the end of the queue
+    ///
+    /// Returns back the `item` if the queue is full
+    pub fn enqueue(&self, item: T) -> Result<(), T> {
+        self.queue.enqueue(item)
+    }
+
+    /// Reserves the slot at the end of the queue so that an item can be constructed in place
+    ///
+    /// Returns `None` if the queue is full. See [`Reservation`](struct.Reservation.html) for
+    /// details.
+    pub fn reserve(&self) -> Option<Reservation<'_, T, N>> {
+        self.queue.reserve()
+    }
+
+    /// Constructs an item in place at the end of the queue
+    ///
+    /// Returns back `f` if the queue is full
+    ///
+    /// # Unsafety
+    ///
+    /// `f` must initialize the slot it's given
+    pub unsafe fn enqueue_with<F>(&self, f: F) -> Result<(), F>
+    where
+        F: FnOnce(&mut MaybeUninit<T>),
+    {
+        self.queue.enqueue_with(f)
+    }
+
+    /// Returns the maximum number of elements the queue can hold
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    /// Returns the number of elements in the queue
+    ///
+    /// See [`mpmc::Queue::len`](../mpmc/struct.Queue.html#method.len) for caveats
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` if the queue has a length of 0
+    ///
+    /// See [`mpmc::Queue::len`](../mpmc/struct.Queue.html#method.len) for caveats
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    #[cfg(not(no_cas))]
+    fn take_consumer(&self) -> bool {
+        !self.consumer_taken.swap(true, Ordering::Acquire)
+    }
+
+    #[cfg(no_cas)]
+    fn take_consumer(&self) -> bool {
+        crate::critical_section::free(|| {
+            if self.consumer_taken.load(Ordering::Relaxed) {
+                false
+            } else {
+                self.consumer_taken.store(true, Ordering::Relaxed);
+                true
+            }
+        })
+    }
+}
+
+impl<T, N> fmt::Debug for Queue<T, N>
+where
+    N: Capacity<T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.queue.fmt(f)
+    }
+}
+
+/// The consumer end point of a MPSC queue
+///
+/// There's at most one `Consumer` per queue at any point in time so this handle can't be cloned.
+pub struct Consumer<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    queue: &'a Queue<T, N>,
+}
+
+impl<'a, T, N> Consumer<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    /// Returns the item in the front of the queue, or `None` if the queue is empty
+    ///
+    /// This also returns `None` if the front slot has been reserved by a producer that has not
+    /// committed the item yet.
+    pub fn dequeue(&mut self) -> Option<T> {
+        // NOTE(unsafe) this is the only `Consumer` of the queue and it's borrowed mutably
+        unsafe { self.queue.queue.dequeue_exclusive() }
+    }
+
+    /// Returns the number of elements in the queue
+    ///
+    /// See [`mpmc::Queue::len`](../mpmc/struct.Queue.html#method.len) for caveats
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` if the queue has a length of 0
+    ///
+    /// See [`mpmc::Queue::len`](../mpmc/struct.Queue.html#method.len) for caveats
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<'a, T, N> Drop for Consumer<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    fn drop(&mut self) {
+        // makes our `dequeue` operations visible to the next consumer
+        self.queue.consumer_taken.store(false, Ordering::Release);
+    }
+}
+
+impl<'a, T, N> fmt::Debug for Consumer<'a, T, N>
+where
+    N: Capacity<T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Consumer")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consts::*;
+
+    use super::Queue;
+
+    #[test]
+    fn sanity() {
+        let q: Queue<i32, U2> = Queue::new();
+        let mut c = q.consumer().unwrap();
+
+        q.enqueue(0).unwrap();
+        q.enqueue(1).unwrap();
+        assert_eq!(q.enqueue(2), Err(2));
+        assert_eq!(c.len(), 2);
+
+        assert_eq!(c.dequeue(), Some(0));
+        assert_eq!(c.dequeue(), Some(1));
+        assert_eq!(c.dequeue(), None);
+    }
+
+    #[test]
+    fn single_consumer() {
+        static Q: Queue<u8, U4> = Queue::NEW;
+
+        let mut c = Q.consumer().unwrap();
+        assert!(Q.consumer().is_none());
+
+        Q.enqueue(0).unwrap();
+        Q.enqueue(1).unwrap();
+        assert_eq!(c.dequeue(), Some(0));
+        drop(c);
+
+        // the consumer can be claimed again once dropped
+        let mut c = Q.consumer().unwrap();
+        assert_eq!(c.dequeue(), Some(1));
+        assert_eq!(c.dequeue(), None);
+    }
+
+    #[test]
+    fn uncommitted_reservation() {
+        let q: Queue<u8, U4> = Queue::new();
+        let mut c = q.consumer().unwrap();
+
+        let mut reservation = q.reserve().unwrap();
+        q.enqueue(1).unwrap();
+
+        // the item behind the reservation is not visible until the reservation is committed
+        assert_eq!(c.dequeue(), None);
+
+        unsafe {
+            reservation.slot().as_mut_ptr().write(0);
+            reservation.commit();
+        }
+        assert_eq!(c.dequeue(), Some(0));
+        assert_eq!(c.dequeue(), Some(1));
+    }
+
+    #[test]
+    fn dropped_reservation() {
+        let q: Queue<u8, U4> = Queue::new();
+        let mut c = q.consumer().unwrap();
+
+        drop(q.reserve().unwrap());
+        q.enqueue(1).unwrap();
+
+        assert_eq!(c.dequeue(), Some(1));
+        assert_eq!(c.dequeue(), None);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let q: Queue<u16, U128> = Queue::new();
+        let mut c = q.consumer().unwrap();
+
+        for i in 0..70_000 {
+            q.enqueue(i as u16).unwrap();
+            q.enqueue(i as u16).unwrap();
+
+            assert_eq!(c.dequeue(), Some(i as u16));
+            assert_eq!(c.dequeue(), Some(i as u16));
+        }
+
+        for i in 0..128 {
+            q.enqueue(i).unwrap();
+        }
+        assert!(q.enqueue(128).is_err());
+        assert_eq!(c.len(), 128);
+    }
+}
@@ -0,0 +1,419 @@
+//! Reference-counted pointers to memory blocks managed by a `Pool`
+//!
+//! An [`Arc`](struct.Arc.html) shares a single memory block between several owners. The reference
+//! count is stored in the memory block itself, next to the data, so the pool must be a
+//! `Pool<ArcInner<T>>`. The block is returned to its pool when the last `Arc` is dropped.
+//!
+//! # Example
+//!
+//! ```
+//! use core::mem::MaybeUninit;
+//!
+//! use heapless::pool::{
+//!     arc::{Arc, ArcInner},
+//!     Node, Pool,
+//! };
+//!
+//! static mut MEMORY: MaybeUninit<[Node<ArcInner<[u8; 4]>>; 2]> = MaybeUninit::uninit();
+//! static POOL: Pool<ArcInner<[u8; 4]>> = Pool::new();
+//!
+//! POOL.grow_exact(unsafe { &mut MEMORY });
+//!
+//! let packet = Arc::new(&POOL, [0, 1, 2, 3]).ok().unwrap();
+//! let logged = packet.clone();
+//! assert_eq!(Arc::strong_count(&packet), 2);
+//!
+//! drop(logged);
+//! assert_eq!(Arc::try_unwrap(packet).ok(), Some([0, 1, 2, 3]));
+//! ```
+
+use core::{
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    mem,
+    ops::Deref,
+    ptr::{self, NonNull},
+    sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+use as_slice::AsSlice;
+
+use super::{Node, Pool};
+
+// a reference count above this value is most likely the result of leaking `Arc`s with
+// `mem::forget`; stop before the count overflows
+const MAX_REFCOUNT: usize = isize::max_value() as usize;
+
+/// The contents of a memory block managed by an `Arc`: a reference count and the data
+pub struct ArcInner<T> {
+    pub(crate) strong: AtomicUsize,
+    pub(crate) data: T,
+}
+
+/// A thread-safe reference-counted pointer to a memory block that belongs to the pool `'a`
+///
+/// Cloning an `Arc` increases the reference count; no memory is allocated.
+pub struct Arc<'a, T> {
+    pool: &'a Pool<ArcInner<T>>,
+    node: NonNull<Node<ArcInner<T>>>,
+}
+
+impl<'a, T> Arc<'a, T> {
+    /// Claims a memory block from `pool` and moves `data` into it
+    ///
+    /// Returns back the `data` if the pool is observed as exhausted
+    pub fn new(pool: &'a Pool<ArcInner<T>>, data: T) -> Result<Self, T> {
+        alloc(pool, data).map(|node| Arc { pool, node })
+    }
+
+    /// Returns the inner value if `this` is the only reference to it
+    ///
+    /// Otherwise, returns back `this`. On success the memory block is returned to the pool.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if unsafe { inner(this.node) }.claim() {
+            let data = unsafe { take(this.pool, this.node) };
+            mem::forget(this);
+            Ok(data)
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Returns a mutable reference to the inner value if `this` is the only reference to it
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if unsafe { inner(this.node) }.is_unique() {
+            Some(unsafe { &mut (*this.node.as_ref().data.get()).data })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of `Arc`s that point to this memory block
+    pub fn strong_count(this: &Self) -> usize {
+        unsafe { inner(this.node) }.strong.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if both `Arc`s point to the same memory block
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.node == other.node
+    }
+}
+
+impl<'a, T> Clone for Arc<'a, T> {
+    fn clone(&self) -> Self {
+        unsafe { inner(self.node) }.increment();
+
+        Arc {
+            pool: self.pool,
+            node: self.node,
+        }
+    }
+}
+
+impl<'a, T> Deref for Arc<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &unsafe { inner(self.node) }.data
+    }
+}
+
+impl<'a, T> Drop for Arc<'a, T> {
+    fn drop(&mut self) {
+        unsafe { release(self.pool, self.node) }
+    }
+}
+
+unsafe impl<'a, T> Send for Arc<'a, T>
+where
+    T: Send + Sync,
+    Pool<ArcInner<T>>: Sync,
+{
+}
+
+unsafe impl<'a, T> Sync for Arc<'a, T>
+where
+    T: Send + Sync,
+    Pool<ArcInner<T>>: Sync,
+{
+}
+
+impl<'a, A> AsSlice for Arc<'a, A>
+where
+    A: AsSlice,
+{
+    type Element = A::Element;
+
+    fn as_slice(&self) -> &[A::Element] {
+        self.deref().as_slice()
+    }
+}
+
+impl<'a, T> fmt::Debug for Arc<'a, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <T as fmt::Debug>::fmt(self, f)
+    }
+}
+
+impl<'a, T> fmt::Display for Arc<'a, T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <T as fmt::Display>::fmt(self, f)
+    }
+}
+
+impl<'a, T> PartialEq for Arc<'a, T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, rhs: &Arc<'a, T>) -> bool {
+        <T as PartialEq>::eq(self, rhs)
+    }
+}
+
+impl<'a, T> Eq for Arc<'a, T> where T: Eq {}
+
+impl<'a, T> PartialOrd for Arc<'a, T>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, rhs: &Arc<'a, T>) -> Option<cmp::Ordering> {
+        <T as PartialOrd>::partial_cmp(self, rhs)
+    }
+}
+
+impl<'a, T> Ord for Arc<'a, T>
+where
+    T: Ord,
+{
+    fn cmp(&self, rhs: &Arc<'a, T>) -> cmp::Ordering {
+        <T as Ord>::cmp(self, rhs)
+    }
+}
+
+impl<'a, T> Hash for Arc<'a, T>
+where
+    T: Hash,
+{
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        <T as Hash>::hash(self, state)
+    }
+}
+
+// NOTE the functions below are shared with `singleton::arc`
+
+// Claims a block from `pool` and initializes it with a reference count of one
+pub(crate) fn alloc<T>(pool: &Pool<ArcInner<T>>, data: T) -> Result<NonNull<Node<ArcInner<T>>>, T> {
+    if let Some(block) = pool.alloc() {
+        let block = block.init(ArcInner {
+            strong: AtomicUsize::new(1),
+            data,
+        });
+
+        Ok(block.node)
+    } else {
+        Err(data)
+    }
+}
+
+// Returns the contents of an initialized block
+pub(crate) unsafe fn inner<'a, T>(node: NonNull<Node<ArcInner<T>>>) -> &'a ArcInner<T> {
+    &*node.as_ref().data.get()
+}
+
+// Drops one reference; the last reference drops the data and returns the block to `pool`
+pub(crate) unsafe fn release<T>(pool: &Pool<ArcInner<T>>, node: NonNull<Node<ArcInner<T>>>) {
+    if inner(node).decrement() {
+        ptr::drop_in_place(&mut (*node.as_ref().data.get()).data);
+        pool.dealloc(node);
+    }
+}
+
+// Moves the data out of a block that has been `claim`-ed and returns the block to `pool`
+pub(crate) unsafe fn take<T>(pool: &Pool<ArcInner<T>>, node: NonNull<Node<ArcInner<T>>>) -> T {
+    let data = ptr::read(&inner(node).data);
+    pool.dealloc(node);
+    data
+}
+
+// NOTE `Ordering`s come from `alloc::sync::Arc`
+impl<T> ArcInner<T> {
+    pub(crate) fn increment(&self) {
+        // new references can only be created from existing ones so this doesn't need to
+        // synchronize with anything
+        if fetch_add(&self.strong) > MAX_REFCOUNT {
+            panic!("reference count overflow");
+        }
+    }
+
+    // Returns `true` if this was the last reference
+    pub(crate) fn decrement(&self) -> bool {
+        if fetch_sub(&self.strong) != 1 {
+            return false;
+        }
+
+        // synchronizes with the `decrement` operations of the other references; all their uses
+        // of the data happen before the data is dropped
+        atomic::fence(Ordering::Acquire);
+
+        true
+    }
+
+    // Takes the last reference; returns `false` if there are other references
+    pub(crate) fn claim(&self) -> bool {
+        compare_exchange(&self.strong, 1, 0)
+    }
+
+    pub(crate) fn is_unique(&self) -> bool {
+        // pairs with the `Release` in `decrement`: the other references are done with the data
+        self.strong.load(Ordering::Acquire) == 1
+    }
+}
+
+// Returns the previous value
+#[cfg(not(no_cas))]
+fn fetch_add(count: &AtomicUsize) -> usize {
+    count.fetch_add(1, Ordering::Relaxed)
+}
+
+// Returns the previous value
+#[cfg(not(no_cas))]
+fn fetch_sub(count: &AtomicUsize) -> usize {
+    count.fetch_sub(1, Ordering::Release)
+}
+
+#[cfg(not(no_cas))]
+fn compare_exchange(count: &AtomicUsize, current: usize, new: usize) -> bool {
+    count
+        .compare_exchange(current, new, Ordering::Acquire, Ordering::Relaxed)
+        .is_ok()
+}
+
+#[cfg(no_cas)]
+fn fetch_add(count: &AtomicUsize) -> usize {
+    crate::critical_section::free(|| {
+        let old = count.load(Ordering::Relaxed);
+        count.store(old.wrapping_add(1), Ordering::Relaxed);
+        old
+    })
+}
+
+#[cfg(no_cas)]
+fn fetch_sub(count: &AtomicUsize) -> usize {
+    crate::critical_section::free(|| {
+        let old = count.load(Ordering::Relaxed);
+        count.store(old.wrapping_sub(1), Ordering::Release);
+        old
+    })
+}
+
+#[cfg(no_cas)]
+fn compare_exchange(count: &AtomicUsize, current: usize, new: usize) -> bool {
+    crate::critical_section::free(|| {
+        if count.load(Ordering::Acquire) == current {
+            count.store(new, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        mem::MaybeUninit,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::{Arc, ArcInner};
+    use crate::pool::{Node, Pool};
+
+    #[test]
+    fn sanity() {
+        static mut MEMORY: MaybeUninit<[Node<ArcInner<u8>>; 1]> = MaybeUninit::uninit();
+        static POOL: Pool<ArcInner<u8>> = Pool::new();
+
+        POOL.grow_exact(unsafe { &mut MEMORY });
+
+        let x = Arc::new(&POOL, 0).unwrap();
+        let y = x.clone();
+        assert!(Arc::ptr_eq(&x, &y));
+        assert_eq!(*y, 0);
+
+        // pool exhausted; cloning doesn't allocate
+        assert_eq!(Arc::new(&POOL, 1).err(), Some(1));
+
+        drop(x);
+        assert_eq!(Arc::strong_count(&y), 1);
+        drop(y);
+
+        // the last reference returned the block to the pool
+        assert_eq!(*Arc::new(&POOL, 1).unwrap(), 1);
+    }
+
+    #[test]
+    fn unique() {
+        static mut MEMORY: MaybeUninit<[Node<ArcInner<u8>>; 1]> = MaybeUninit::uninit();
+        static POOL: Pool<ArcInner<u8>> = Pool::new();
+
+        POOL.grow_exact(unsafe { &mut MEMORY });
+
+        let mut x = Arc::new(&POOL, 0).unwrap();
+        *Arc::get_mut(&mut x).unwrap() = 1;
+
+        let y = x.clone();
+        assert!(Arc::get_mut(&mut x).is_none());
+
+        let x = Arc::try_unwrap(x).err().unwrap();
+        drop(y);
+        assert_eq!(Arc::try_unwrap(x).ok(), Some(1));
+
+        // `try_unwrap` returned the block to the pool
+        assert!(Arc::new(&POOL, 2).is_ok());
+    }
+
+    #[test]
+    fn destructors() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct X;
+
+        impl X {
+            fn new() -> X {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+                X
+            }
+        }
+
+        impl Drop for X {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        static mut MEMORY: MaybeUninit<[Node<ArcInner<X>>; 1]> = MaybeUninit::uninit();
+        static POOL: Pool<ArcInner<X>> = Pool::new();
+
+        POOL.grow_exact(unsafe { &mut MEMORY });
+
+        let x = Arc::new(&POOL, X::new()).ok().unwrap();
+        let y = x.clone();
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+
+        drop(x);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+
+        // this runs `X`'s destructor
+        drop(y);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    }
+}
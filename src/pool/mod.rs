@@ -1,6 +1,7 @@
 //! A heap-less, interrupt-safe, lock-free memory pool (\*)
 //!
-//! (\*) Currently, the implementation is only lock-free *and* `Sync` on ARMv7-M devices
+//! (\*) Currently, the implementation is only lock-free *and* `Sync` on ARMv7-M(R), x86_64 and
+//! AArch64 devices. On ARMv6-M and MSP430 devices it's `Sync` but uses critical sections instead.
 //!
 //! # Examples
 //!
@@ -48,16 +49,50 @@
 //! }
 //! ```
 //!
+//! # Reference counting
+//!
+//! `Box` has unique ownership of its memory block. To share a block between several owners (e.g.
+//! a received packet that's both logged and processed by the network stack) use
+//! [`arc::Arc`](arc/struct.Arc.html), or [`singleton::arc::Arc`](singleton/arc/struct.Arc.html)
+//! with the `arc_pool!` macro. These pools hand out `ArcInner<T>` blocks: the data plus an
+//! atomic reference count. The block is returned to the pool when the last `Arc` is dropped.
+//!
+//! # Size classes
+//!
+//! [`size_class::SizeClasses`](size_class/struct.SizeClasses.html) combines several pools of byte
+//! blocks (e.g. 32, 128 and 512 bytes) into an allocator that hands out the smallest block that
+//! fits a request, falling back to larger blocks when a class is exhausted. It also implements
+//! `GlobalAlloc`.
+//!
+//! # Statistics
+//!
+//! With the `pool-stats` Cargo feature enabled, `Pool::stats` (and `singleton::Pool::stats`)
+//! report the capacity of the pool, the number of free and in-use blocks, the lowest number of free
+//! blocks the pool has had (low-water mark) and the number of failed allocations. Use these to
+//! size pools from field data. The feature is disabled by default because keeping the counters
+//! up to date adds a few atomic operations to `alloc` and `free`.
+//!
 //! # Portability
 //!
 //! This pool internally uses a Treiber stack which is known to be susceptible to the ABA problem.
-//! The only counter measure against the ABA problem that this implementation currently takes is
-//! relying on LL/SC (Link-local / Store-conditional) instructions being used to implement CAS loops
-//! on the target architecture (see section on ['Soundness'](#soundness) for more information). For
-//! this reason, `Pool` only implements `Sync` when compiling for ARMv7-M.
+//! On ARMv7-M and ARMv7-R the counter measure against the ABA problem that this implementation
+//! takes is relying on LL/SC (Link-local / Store-conditional) instructions being used to implement
+//! CAS loops on the target architecture (see section on ['Soundness'](#soundness) for more
+//! information).
 //!
-//! Also note that ARMv6-M architecture lacks the primitives for CAS loops so this module does *not*
-//! exist for `thumbv6m-none-eabi`.
+//! On x86_64 and AArch64 the head of the stack is instead a *tagged* pointer: a 32-bit tag, that's
+//! incremented on every `push` and `pop`, packed with a 32-bit offset from an internal `static`
+//! variable. A `pop` that races with other `pop` / `push` operations will see a different tag and
+//! retry. The offset limits where the memory blocks can live: they must be within +/-2 GiB of the
+//! program's static data. `grow` and `grow_exact` skip blocks that are out of range, which never
+//! happens when the memory comes from a `static` variable.
+//!
+//! On any other architecture `Pool` does not implement `Sync`.
+//!
+//! Also note that the ARMv6-M (`thumbv6m-none-eabi`) and MSP430 (`msp430-none-elf`)
+//! architectures lack the primitives for CAS loops. On those single core architectures the `push`
+//! and `pop` operations are instead executed inside an interrupt-free critical section, which
+//! also rules out the ABA problem, so `Pool` is `Sync` but *not* lock-free there.
 //!
 //! # Soundness
 //!
@@ -154,36 +189,59 @@
 //!
 //! [1]: https://static.docs.arm.com/ddi0403/eb/DDI0403E_B_armv7m_arm.pdf
 
-#[cfg(not(armv6m))]
-use core::{any::TypeId, mem, sync::atomic::Ordering};
 use core::{
+    any::TypeId,
     cell::UnsafeCell,
     cmp, fmt,
     hash::{Hash, Hasher},
     marker::PhantomData,
-    mem::MaybeUninit,
+    mem::{self, MaybeUninit},
     ops::{Deref, DerefMut},
     ptr::{self, NonNull},
-    sync::atomic::AtomicPtr,
+    sync::atomic::{AtomicPtr, Ordering},
 };
 
 use as_slice::{AsMutSlice, AsSlice};
 
+#[cfg(feature = "pool-stats")]
+use self::stats::Counters;
+#[cfg(feature = "pool-stats")]
+pub use self::stats::Stats;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use self::tagged::{AtomicTaggedPtr, TaggedPtr};
+
+pub mod arc;
 pub mod singleton;
+pub mod size_class;
+#[cfg(feature = "pool-stats")]
+mod stats;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+mod tagged;
 
 /// A lock-free memory pool
 pub struct Pool<T> {
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
     head: AtomicPtr<Node<T>>,
 
-    // Current implementation is unsound on architectures that don't have LL/SC semantics so this
-    // struct is not `Sync` on those platforms
+    // a tagged pointer protects the stack from the ABA problem on these architectures
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    head: AtomicTaggedPtr<Node<T>>,
+
+    #[cfg(feature = "pool-stats")]
+    stats: Counters,
+
+    // Current implementation is unsound on architectures that don't have LL/SC semantics, or
+    // tagged pointers, so this struct is not `Sync` on those platforms
     _not_send_or_sync: PhantomData<*const ()>,
 }
 
-// NOTE: Here we lie about `Pool` implementing `Sync` on x86_64. This is not true but it lets us
-// test the `pool!` and `singleton::Pool` abstractions. We just have to be careful not to use the
-// pool in a multi-threaded context
-#[cfg(any(armv7m, armv7r, test))]
+#[cfg(any(
+    armv7m,
+    armv7r,
+    no_cas,
+    target_arch = "x86_64",
+    target_arch = "aarch64"
+))]
 unsafe impl<T> Sync for Pool<T> {}
 
 unsafe impl<T> Send for Pool<T> {}
@@ -192,7 +250,13 @@ impl<T> Pool<T> {
     /// Creates a new empty pool
     pub const fn new() -> Self {
         Pool {
+            #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
             head: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+            head: AtomicTaggedPtr::null(),
+
+            #[cfg(feature = "pool-stats")]
+            stats: Counters::new(),
 
             _not_send_or_sync: PhantomData,
         }
@@ -205,11 +269,17 @@ impl<T> Pool<T> {
     /// *NOTE:* This method does *not* have bounded execution time because it contains a CAS loop
     pub fn alloc(&self) -> Option<Box<T, Uninit>> {
         if let Some(node) = self.pop() {
+            #[cfg(feature = "pool-stats")]
+            self.stats.alloc();
+
             Some(Box {
                 node,
                 _state: PhantomData,
             })
         } else {
+            #[cfg(feature = "pool-stats")]
+            self.stats.alloc_failed();
+
             None
         }
     }
@@ -229,7 +299,15 @@ impl<T> Pool<T> {
             }
         }
 
-        self.push(value.node)
+        self.dealloc(value.node)
+    }
+
+    /// Returns the usage statistics of the pool
+    ///
+    /// *NOTE:* This method is only available when the `pool-stats` Cargo feature is enabled
+    #[cfg(feature = "pool-stats")]
+    pub fn stats(&self) -> Stats {
+        self.stats.snapshot()
     }
 
     /// Increases the capacity of the pool
@@ -237,6 +315,10 @@ impl<T> Pool<T> {
     /// This method might *not* fully utilize the given memory block due to alignment requirements.
     ///
     /// This method returns the number of *new* blocks that can be allocated.
+    ///
+    /// *NOTE:* On x86_64 and AArch64 the blocks must be within +/-2 GiB of the program's static
+    /// data; blocks outside that range are not added to the pool. Memory placed in a `static`
+    /// variable always meets this requirement. See ['Portability'](index.html#portability).
     pub fn grow(&self, memory: &'static mut [u8]) -> usize {
         let mut p = memory.as_mut_ptr();
         let mut len = memory.len();
@@ -259,39 +341,71 @@ impl<T> Pool<T> {
 
         let mut n = 0;
         while len >= sz {
-            self.push(unsafe { NonNull::new_unchecked(p as *mut _) });
-            n += 1;
+            if Self::is_addressable(p as *mut _) {
+                self.push(unsafe { NonNull::new_unchecked(p as *mut _) });
+                n += 1;
+            }
 
             p = unsafe { p.add(sz) };
             len -= sz;
         }
 
+        #[cfg(feature = "pool-stats")]
+        self.stats.grow(n);
+
         n
     }
 
     /// Increases the capacity of the pool
     ///
     /// Unlike [`Pool.grow`](struct.Pool.html#method.grow) this method fully utilizes the given
-    /// memory block
+    /// memory block. The same range restriction applies on x86_64 and AArch64.
     pub fn grow_exact<A>(&self, memory: &'static mut MaybeUninit<A>) -> usize
     where
         A: AsMutSlice<Element = Node<T>>,
     {
         let nodes = unsafe { (*memory.as_mut_ptr()).as_mut_slice() };
-        let cap = nodes.len();
+        let mut n = 0;
         for p in nodes {
-            self.push(NonNull::from(p))
+            if Self::is_addressable(p) {
+                self.push(NonNull::from(p));
+                n += 1;
+            }
         }
-        cap
+        #[cfg(feature = "pool-stats")]
+        self.stats.grow(n);
+
+        n
     }
 
+    // Returns a block that was claimed with `alloc` to the pool
+    fn dealloc(&self, node: NonNull<Node<T>>) {
+        #[cfg(feature = "pool-stats")]
+        self.stats.dealloc();
+
+        self.push(node)
+    }
+
+    #[cfg(no_cas)]
+    fn pop(&self) -> Option<NonNull<Node<T>>> {
+        crate::critical_section::free(|| {
+            let head = NonNull::new(self.head.load(Ordering::Relaxed))?;
+            self.head.store(
+                unsafe { head.as_ref().next.load(Ordering::Relaxed) },
+                Ordering::Relaxed,
+            );
+            Some(head)
+        })
+    }
+
+    #[cfg(not(any(no_cas, target_arch = "x86_64", target_arch = "aarch64")))]
     fn pop(&self) -> Option<NonNull<Node<T>>> {
         // NOTE `Ordering`s come from crossbeam's (v0.6.0) `TreiberStack`
 
         loop {
             let head = self.head.load(Ordering::Acquire);
             if let Some(nn_head) = NonNull::new(head) {
-                let next = unsafe { (*head).next };
+                let next = unsafe { (*head).next.load(Ordering::Relaxed) };
 
                 match self.head.compare_exchange_weak(
                     head,
@@ -310,12 +424,54 @@ impl<T> Pool<T> {
         }
     }
 
-    fn push(&self, mut new_head: NonNull<Node<T>>) {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn pop(&self) -> Option<NonNull<Node<T>>> {
+        // NOTE `Ordering`s come from crossbeam's (v0.6.0) `TreiberStack`
+
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if let Some(nn_head) = NonNull::new(head.as_ptr()) {
+                // NOTE another thread may have popped this node (and even pushed it back) since we
+                // loaded `head`; in that case `next` is stale but the tag makes the CAS fail
+                let next = unsafe { nn_head.as_ref().next.load(Ordering::Relaxed) };
+
+                match self.head.compare_exchange_weak(
+                    head,
+                    tag(next, head.tag().wrapping_add(1)),
+                    Ordering::Release, // success
+                    Ordering::Relaxed, // failure
+                ) {
+                    Ok(_) => break Some(nn_head),
+                    // head was changed by some other thread
+                    Err(_) => continue,
+                }
+            } else {
+                // stack is observed as empty
+                break None;
+            }
+        }
+    }
+
+    #[cfg(no_cas)]
+    fn push(&self, new_head: NonNull<Node<T>>) {
+        crate::critical_section::free(|| {
+            unsafe {
+                new_head
+                    .as_ref()
+                    .next
+                    .store(self.head.load(Ordering::Relaxed), Ordering::Relaxed)
+            }
+            self.head.store(new_head.as_ptr(), Ordering::Relaxed);
+        })
+    }
+
+    #[cfg(not(any(no_cas, target_arch = "x86_64", target_arch = "aarch64")))]
+    fn push(&self, new_head: NonNull<Node<T>>) {
         // NOTE `Ordering`s come from crossbeam's (v0.6.0) `TreiberStack`
 
         let mut head = self.head.load(Ordering::Relaxed);
         loop {
-            unsafe { new_head.as_mut().next = head }
+            unsafe { new_head.as_ref().next.store(head, Ordering::Relaxed) }
 
             match self.head.compare_exchange_weak(
                 head,
@@ -329,13 +485,61 @@ impl<T> Pool<T> {
             }
         }
     }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn push(&self, new_head: NonNull<Node<T>>) {
+        // NOTE `Ordering`s come from crossbeam's (v0.6.0) `TreiberStack`
+
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe {
+                new_head
+                    .as_ref()
+                    .next
+                    .store(head.as_ptr(), Ordering::Relaxed)
+            }
+
+            match self.head.compare_exchange_weak(
+                head,
+                tag(new_head.as_ptr(), head.tag().wrapping_add(1)),
+                Ordering::Release, // success
+                Ordering::Relaxed, // failure
+            ) {
+                Ok(_) => return,
+                // head changed
+                Err(p) => head = p,
+            }
+        }
+    }
+
+    // Returns `false` if the node can't be stored in the stack
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn is_addressable(node: *mut Node<T>) -> bool {
+        TaggedPtr::new(node, 0).is_some()
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn is_addressable(_: *mut Node<T>) -> bool {
+        true
+    }
+}
+
+// NOTE `grow` and `grow_exact` only add addressable nodes to the stack so this can't fail
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn tag<T>(ptr: *mut Node<T>, tag: u32) -> TaggedPtr<Node<T>> {
+    match TaggedPtr::new(ptr, tag) {
+        Some(p) => p,
+        None => unreachable!(),
+    }
 }
 
 /// Unfortunate implementation detail required to use the
 /// [`Pool.grow_exact`](struct.Pool.html#method.grow_exact) method
+// NOTE `size_class` relies on `data` being at the start of the node
+#[repr(C)]
 pub struct Node<T> {
     data: UnsafeCell<T>,
-    next: *mut Node<T>,
+    next: AtomicPtr<Node<T>>,
 }
 
 /// A memory block
@@ -525,6 +729,47 @@ mod tests {
         assert_eq!(*POOL.alloc().unwrap().init(1), 1);
     }
 
+    #[cfg(feature = "pool-stats")]
+    #[test]
+    fn stats() {
+        static mut MEMORY: MaybeUninit<[Node<u32>; 4]> = MaybeUninit::uninit();
+
+        static POOL: Pool<u32> = Pool::new();
+
+        POOL.grow_exact(unsafe { &mut MEMORY });
+
+        let x = POOL.alloc().unwrap().init(0);
+        let y = POOL.alloc().unwrap().init(1);
+        let z = POOL.alloc().unwrap().init(2);
+        POOL.free(x);
+        POOL.free(y);
+
+        let stats = POOL.stats();
+        assert_eq!(stats.capacity, 4);
+        assert_eq!(stats.free, 3);
+        assert_eq!(stats.in_use, 1);
+        assert_eq!(stats.low_water_mark, 1);
+        assert_eq!(stats.failed_allocs, 0);
+
+        let a = POOL.alloc().unwrap();
+        let b = POOL.alloc().unwrap();
+        let c = POOL.alloc().unwrap();
+        assert!(POOL.alloc().is_none());
+        assert!(POOL.alloc().is_none());
+
+        let stats = POOL.stats();
+        assert_eq!(stats.free, 0);
+        assert_eq!(stats.in_use, 4);
+        assert_eq!(stats.low_water_mark, 0);
+        assert_eq!(stats.failed_allocs, 2);
+
+        POOL.free(a);
+        POOL.free(b);
+        POOL.free(c);
+        POOL.free(z);
+        assert_eq!(POOL.stats().free, 4);
+    }
+
     #[test]
     fn destructors() {
         static COUNT: AtomicUsize = AtomicUsize::new(0);
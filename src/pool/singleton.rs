@@ -14,8 +14,16 @@ use as_slice::{AsMutSlice, AsSlice};
 
 use super::{Init, Node, Uninit};
 
+pub mod arc;
+
 /// Instantiates a pool as a global singleton
-#[cfg(any(armv7m, armv7r, test))]
+#[cfg(any(
+    armv7m,
+    armv7r,
+    no_cas,
+    target_arch = "x86_64",
+    target_arch = "aarch64"
+))]
 #[macro_export]
 macro_rules! pool {
     ($(#[$($attr:tt)*])* $ident:ident: $ty:ty) => {
@@ -76,6 +84,14 @@ pub trait Pool {
     {
         Self::ptr().grow_exact(memory)
     }
+
+    /// Returns the usage statistics of the pool
+    ///
+    /// *NOTE:* This method is only available when the `pool-stats` Cargo feature is enabled
+    #[cfg(feature = "pool-stats")]
+    fn stats() -> super::Stats {
+        Self::ptr().stats()
+    }
 }
 
 /// A memory block that belongs to the global memory pool, `POOL`
@@ -192,7 +208,7 @@ where
             }
         }
 
-        P::ptr().push(self.inner.node)
+        P::ptr().dealloc(self.inner.node)
     }
 }
 
@@ -314,6 +330,27 @@ mod tests {
         assert_eq!(*A::alloc().unwrap().init(1), 1);
     }
 
+    #[cfg(feature = "pool-stats")]
+    #[test]
+    fn stats() {
+        static mut MEMORY: [u8; 31] = [0; 31];
+
+        pool!(A: u8);
+
+        A::grow(unsafe { &mut MEMORY });
+        assert_eq!(A::stats().capacity, 1);
+
+        let x = A::alloc().unwrap().init(0);
+        assert!(A::alloc().is_none());
+        assert_eq!(A::stats().in_use, 1);
+        assert_eq!(A::stats().failed_allocs, 1);
+
+        // dropping a `Box` returns the block to the pool
+        drop(x);
+        assert_eq!(A::stats().free, 1);
+        assert_eq!(A::stats().low_water_mark, 0);
+    }
+
     #[test]
     fn destructors() {
         static COUNT: AtomicUsize = AtomicUsize::new(0);
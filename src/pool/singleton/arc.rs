@@ -0,0 +1,399 @@
+//! Reference-counted pointers to memory blocks that belong to a global singleton pool
+//!
+//! This is the singleton version of [`pool::arc`](../../arc/index.html). The pool is declared with
+//! the `arc_pool!` macro and the `Arc` pointers don't need to carry a reference to it.
+//!
+//! # Example
+//!
+//! ``` ignore
+//! #![no_main]
+//! #![no_std]
+//!
+//! use heapless::{arc_pool, pool::singleton::arc::{Arc, Pool as _}};
+//!
+//! // instantiate a memory pool of reference-counted `[u8; 128]` blocks as a global singleton
+//! arc_pool!(
+//!     // attributes can be used here
+//!     // #[link_section = ".ccram.P"]
+//!     P: [u8; 128]
+//! );
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     static mut MEMORY: [u8; 1024] = [0; 1024];
+//!
+//!     // increase the capacity of the pool by ~7 blocks
+//!     P::grow(MEMORY);
+//!
+//!     let packet: Arc<P> = Arc::new([0; 128]).ok().unwrap();
+//!
+//!     // hand a copy of the pointer to the logger; this doesn't copy the buffer
+//!     let for_logger = packet.clone();
+//!
+//!     // .. the block is returned to `P` when both `packet` and `for_logger` are dropped ..
+//! }
+//! ```
+
+use core::{
+    cmp, fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::Ordering,
+};
+
+use as_slice::{AsMutSlice, AsSlice};
+
+use crate::pool::{
+    arc::{self as non_singleton, ArcInner},
+    Node,
+};
+
+/// Instantiates a pool of reference-counted memory blocks as a global singleton
+#[cfg(any(
+    armv7m,
+    armv7r,
+    no_cas,
+    target_arch = "x86_64",
+    target_arch = "aarch64"
+))]
+#[macro_export]
+macro_rules! arc_pool {
+    ($(#[$($attr:tt)*])* $ident:ident: $ty:ty) => {
+        pub struct $ident;
+
+        impl $crate::pool::singleton::arc::Pool for $ident {
+            type Data = $ty;
+
+            fn ptr() -> &'static $crate::pool::Pool<$crate::pool::arc::ArcInner<$ty>> {
+                $(#[$($attr)*])*
+                static $ident: $crate::pool::Pool<$crate::pool::arc::ArcInner<$ty>> =
+                    $crate::pool::Pool::new();
+
+                &$ident
+            }
+        }
+    };
+}
+
+/// A global singleton memory pool of reference-counted memory blocks
+pub trait Pool {
+    /// The type of data that can be allocated on this pool
+    type Data: 'static;
+
+    #[doc(hidden)]
+    fn ptr() -> &'static crate::pool::Pool<ArcInner<Self::Data>>;
+
+    /// Increases the capacity of the pool
+    ///
+    /// This method might *not* fully utilize the given memory block due to alignment requirements
+    ///
+    /// This method returns the number of *new* blocks that can be allocated.
+    fn grow(memory: &'static mut [u8]) -> usize {
+        Self::ptr().grow(memory)
+    }
+
+    /// Increases the capacity of the pool
+    ///
+    /// Unlike [`Pool.grow`](trait.Pool.html#method.grow_exact) this method fully utilizes the given
+    /// memory block
+    fn grow_exact<A>(memory: &'static mut MaybeUninit<A>) -> usize
+    where
+        A: AsMutSlice<Element = Node<ArcInner<Self::Data>>>,
+    {
+        Self::ptr().grow_exact(memory)
+    }
+
+    /// Returns the usage statistics of the pool
+    ///
+    /// *NOTE:* This method is only available when the `pool-stats` Cargo feature is enabled
+    #[cfg(feature = "pool-stats")]
+    fn stats() -> crate::pool::Stats {
+        Self::ptr().stats()
+    }
+}
+
+/// A thread-safe reference-counted pointer to a memory block that belongs to the global memory
+/// pool, `POOL`
+///
+/// Cloning an `Arc` increases the reference count; no memory is allocated.
+pub struct Arc<POOL>
+where
+    POOL: Pool,
+{
+    _pool: PhantomData<POOL>,
+    node: NonNull<Node<ArcInner<POOL::Data>>>,
+}
+
+impl<P> Arc<P>
+where
+    P: Pool,
+{
+    /// Claims a memory block from the pool and moves `data` into it
+    ///
+    /// Returns back the `data` if the pool is observed as exhausted
+    pub fn new(data: P::Data) -> Result<Self, P::Data> {
+        non_singleton::alloc(P::ptr(), data).map(|node| Arc {
+            _pool: PhantomData,
+            node,
+        })
+    }
+
+    /// Returns the inner value if `this` is the only reference to it
+    ///
+    /// Otherwise, returns back `this`. On success the memory block is returned to the pool.
+    pub fn try_unwrap(this: Self) -> Result<P::Data, Self> {
+        if unsafe { non_singleton::inner(this.node) }.claim() {
+            let data = unsafe { non_singleton::take(P::ptr(), this.node) };
+            mem::forget(this);
+            Ok(data)
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Returns a mutable reference to the inner value if `this` is the only reference to it
+    pub fn get_mut(this: &mut Self) -> Option<&mut P::Data> {
+        if unsafe { non_singleton::inner(this.node) }.is_unique() {
+            Some(unsafe { &mut (*this.node.as_ref().data.get()).data })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of `Arc`s that point to this memory block
+    pub fn strong_count(this: &Self) -> usize {
+        unsafe { non_singleton::inner(this.node) }
+            .strong
+            .load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if both `Arc`s point to the same memory block
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.node == other.node
+    }
+}
+
+impl<P> Clone for Arc<P>
+where
+    P: Pool,
+{
+    fn clone(&self) -> Self {
+        unsafe { non_singleton::inner(self.node) }.increment();
+
+        Arc {
+            _pool: PhantomData,
+            node: self.node,
+        }
+    }
+}
+
+impl<P> Deref for Arc<P>
+where
+    P: Pool,
+{
+    type Target = P::Data;
+
+    fn deref(&self) -> &P::Data {
+        &unsafe { non_singleton::inner(self.node) }.data
+    }
+}
+
+impl<P> Drop for Arc<P>
+where
+    P: Pool,
+{
+    fn drop(&mut self) {
+        unsafe { non_singleton::release(P::ptr(), self.node) }
+    }
+}
+
+unsafe impl<P> Send for Arc<P>
+where
+    P: Pool,
+    P::Data: Send + Sync,
+{
+}
+
+unsafe impl<P> Sync for Arc<P>
+where
+    P: Pool,
+    P::Data: Send + Sync,
+{
+}
+
+impl<P, T> AsSlice for Arc<P>
+where
+    P: Pool,
+    P::Data: AsSlice<Element = T>,
+{
+    type Element = T;
+
+    fn as_slice(&self) -> &[T] {
+        self.deref().as_slice()
+    }
+}
+
+impl<P> fmt::Debug for Arc<P>
+where
+    P: Pool,
+    P::Data: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <P::Data as fmt::Debug>::fmt(self, f)
+    }
+}
+
+impl<P> fmt::Display for Arc<P>
+where
+    P: Pool,
+    P::Data: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <P::Data as fmt::Display>::fmt(self, f)
+    }
+}
+
+impl<P> PartialEq for Arc<P>
+where
+    P: Pool,
+    P::Data: PartialEq,
+{
+    fn eq(&self, rhs: &Arc<P>) -> bool {
+        <P::Data as PartialEq>::eq(self, rhs)
+    }
+}
+
+impl<P> Eq for Arc<P>
+where
+    P: Pool,
+    P::Data: Eq,
+{
+}
+
+impl<P> PartialOrd for Arc<P>
+where
+    P: Pool,
+    P::Data: PartialOrd,
+{
+    fn partial_cmp(&self, rhs: &Arc<P>) -> Option<cmp::Ordering> {
+        <P::Data as PartialOrd>::partial_cmp(self, rhs)
+    }
+}
+
+impl<P> Ord for Arc<P>
+where
+    P: Pool,
+    P::Data: Ord,
+{
+    fn cmp(&self, rhs: &Arc<P>) -> cmp::Ordering {
+        <P::Data as Ord>::cmp(self, rhs)
+    }
+}
+
+impl<P> Hash for Arc<P>
+where
+    P: Pool,
+    P::Data: Hash,
+{
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        <P::Data as Hash>::hash(self, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{Arc, Pool};
+
+    #[test]
+    fn sanity() {
+        static mut MEMORY: [u8; 31] = [0; 31];
+
+        arc_pool!(A: u8);
+
+        // empty pool
+        assert_eq!(Arc::<A>::new(0).err(), Some(0));
+
+        A::grow(unsafe { &mut MEMORY });
+
+        let x = Arc::<A>::new(0).unwrap();
+        let y = x.clone();
+        assert!(Arc::ptr_eq(&x, &y));
+        assert_eq!(Arc::strong_count(&x), 2);
+
+        // pool exhausted
+        assert!(Arc::<A>::new(1).is_err());
+
+        drop(x);
+        assert_eq!(*y, 0);
+        drop(y);
+
+        // should be possible to allocate again
+        assert_eq!(*Arc::<A>::new(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn unique() {
+        static mut MEMORY: [u8; 31] = [0; 31];
+
+        arc_pool!(A: u8);
+
+        A::grow(unsafe { &mut MEMORY });
+
+        let mut x = Arc::<A>::new(0).unwrap();
+        *Arc::get_mut(&mut x).unwrap() = 1;
+
+        let y = x.clone();
+        assert!(Arc::get_mut(&mut x).is_none());
+
+        let x = Arc::try_unwrap(x).err().unwrap();
+        drop(y);
+        assert_eq!(Arc::try_unwrap(x).ok(), Some(1));
+
+        // `try_unwrap` returned the block to the pool
+        assert!(Arc::<A>::new(2).is_ok());
+    }
+
+    #[test]
+    fn destructors() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct X;
+
+        impl X {
+            fn new() -> X {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+                X
+            }
+        }
+
+        impl Drop for X {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        arc_pool!(A: X);
+        static mut MEMORY: [u8; 31] = [0; 31];
+
+        A::grow(unsafe { &mut MEMORY });
+
+        let x = Arc::<A>::new(X::new()).ok().unwrap();
+        let y = x.clone();
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+
+        drop(x);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+
+        // this runs `X`'s destructor
+        drop(y);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,390 @@
+//! An allocator that combines several `Pool`s of byte blocks of different sizes
+//!
+//! Each pool is a *size class*. [`SizeClasses::alloc`](struct.SizeClasses.html#method.alloc)
+//! claims a block from the smallest class that can hold the requested number of bytes; if that
+//! class is exhausted it falls back to the next larger class, and so on. This gives `malloc`-like
+//! flexibility with the bounded, deterministic behavior of fixed-size pools.
+//!
+//! The size classes are given as a tuple of up to 8 `Pool<SizeClass<A>>`s, where `A` is a byte
+//! array, sorted by block size in ascending order.
+//!
+//! # Example
+//!
+//! ```
+//! use heapless::pool::{
+//!     size_class::{SizeClass, SizeClasses},
+//!     Pool,
+//! };
+//!
+//! static ALLOC: SizeClasses<(
+//!     Pool<SizeClass<[u8; 32]>>,
+//!     Pool<SizeClass<[u8; 128]>>,
+//!     Pool<SizeClass<[u8; 512]>>,
+//! )> = SizeClasses::new((Pool::new(), Pool::new(), Pool::new()));
+//!
+//! static mut SMALL: [u8; 256] = [0; 256];
+//! static mut LARGE: [u8; 1024] = [0; 1024];
+//!
+//! // classes that are never grown are always exhausted
+//! ALLOC.classes().0.grow(unsafe { &mut SMALL });
+//! ALLOC.classes().2.grow(unsafe { &mut LARGE });
+//!
+//! let header = ALLOC.alloc(20).unwrap();
+//! assert_eq!(header.len(), 20);
+//! assert_eq!(header.capacity(), 32);
+//!
+//! // there are no 128-byte blocks so this falls back to the 512-byte class
+//! let payload = ALLOC.alloc(100).unwrap();
+//! assert_eq!(payload.capacity(), 512);
+//!
+//! // no class is large enough
+//! assert!(ALLOC.alloc(1024).is_none());
+//! ```
+//!
+//! # `GlobalAlloc`
+//!
+//! `SizeClasses` implements `GlobalAlloc` so it can be registered as the `#[global_allocator]`
+//! and back the `alloc` crate (`Box`, `Vec`, etc.) with pool memory. Requests that no class can
+//! satisfy, including requests whose alignment exceeds the alignment of a pointer, return a null
+//! pointer. The unstable `Allocator` trait is not implemented.
+//!
+//! ``` ignore
+//! #![no_std]
+//!
+//! extern crate alloc;
+//!
+//! use heapless::pool::{
+//!     size_class::{SizeClass, SizeClasses},
+//!     Pool,
+//! };
+//!
+//! #[global_allocator]
+//! static ALLOC: SizeClasses<(
+//!     Pool<SizeClass<[u8; 32]>>,
+//!     Pool<SizeClass<[u8; 128]>>,
+//!     Pool<SizeClass<[u8; 512]>>,
+//! )> = SizeClasses::new((Pool::new(), Pool::new(), Pool::new()));
+//! ```
+//!
+//! # Memory overhead
+//!
+//! Each [`SizeClass`](struct.SizeClass.html) block starts with a pointer-sized header that holds
+//! the index of its size class. This is how `SizeClasses` finds the pool a block must be returned
+//! to.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt, mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use super::{Node, Pool};
+use crate::sealed::pool::Classes;
+
+// NOTE `Node` and `SizeClass` are `repr(C)` so, as long as the bytes don't need more alignment
+// than a `usize`, the class index sits right before them and the node starts at the class index
+const HEADER: usize = mem::size_of::<usize>();
+
+/// A memory block of a size class: the index of the class followed by the bytes, `A`
+///
+/// Use `Pool<SizeClass<A>>` as the pools of a `SizeClasses` allocator.
+#[repr(C)]
+pub struct SizeClass<A> {
+    class: usize,
+    bytes: A,
+}
+
+/// An allocator made of several size classes, `C`
+///
+/// See the [module level documentation](index.html) for details.
+pub struct SizeClasses<C> {
+    classes: C,
+}
+
+impl<C> SizeClasses<C> {
+    /// Creates an allocator from a tuple of pools sorted by block size in ascending order
+    pub const fn new(classes: C) -> Self {
+        SizeClasses { classes }
+    }
+
+    /// Returns the pools that make up this allocator
+    ///
+    /// Use this to `grow` the individual pools
+    pub fn classes(&self) -> &C {
+        &self.classes
+    }
+}
+
+impl<C> SizeClasses<C>
+where
+    C: Classes,
+{
+    /// Claims a memory block that can hold at least `size` bytes
+    ///
+    /// The block comes from the smallest class that fits `size`; if that class is exhausted the
+    /// next larger classes are tried in order. Returns `None` if no class is large enough or all
+    /// the classes that are large enough are exhausted.
+    ///
+    /// The returned block is `size` bytes long and zeroed. It's returned to its pool on `drop`.
+    ///
+    /// *NOTE:* This method does *not* have bounded execution time because it contains CAS loops
+    pub fn alloc(&self, size: usize) -> Option<Block<'_, C>> {
+        let ptr = self.alloc_raw(size)?;
+
+        unsafe { ptr::write_bytes(ptr.as_ptr(), 0, size) }
+
+        Some(Block {
+            classes: &self.classes,
+            ptr,
+            len: size,
+        })
+    }
+
+    /// Returns the size, in bytes, of the largest block this allocator can hand out
+    pub fn max_size(&self) -> usize {
+        (0..C::LEN)
+            .map(|class| self.classes.block_size(class))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn alloc_raw(&self, size: usize) -> Option<NonNull<u8>> {
+        (0..C::LEN)
+            .filter(|&class| self.classes.block_size(class) >= size)
+            .filter_map(|class| self.classes.alloc(class))
+            .next()
+    }
+}
+
+unsafe impl<C> GlobalAlloc for SizeClasses<C>
+where
+    C: Classes,
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.align() > HEADER {
+            return ptr::null_mut();
+        }
+
+        self.alloc_raw(layout.size())
+            .map(NonNull::as_ptr)
+            .unwrap_or(ptr::null_mut())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _: Layout) {
+        release(&self.classes, NonNull::new_unchecked(ptr))
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // the block may already be large enough
+        if self.classes.block_size(class(NonNull::new_unchecked(ptr))) >= new_size {
+            return ptr;
+        }
+
+        let new = GlobalAlloc::alloc(
+            self,
+            Layout::from_size_align_unchecked(new_size, layout.align()),
+        );
+
+        if !new.is_null() {
+            ptr::copy_nonoverlapping(ptr, new, layout.size());
+            self.dealloc(ptr, layout);
+        }
+
+        new
+    }
+}
+
+/// A memory block claimed from a `SizeClasses` allocator
+///
+/// The block is returned to its pool when dropped.
+pub struct Block<'a, C>
+where
+    C: Classes,
+{
+    classes: &'a C,
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl<'a, C> Block<'a, C>
+where
+    C: Classes,
+{
+    /// Returns the size, in bytes, of the underlying memory block
+    ///
+    /// This can be larger than the requested size
+    pub fn capacity(&self) -> usize {
+        self.classes.block_size(unsafe { class(self.ptr) })
+    }
+}
+
+impl<'a, C> Deref for Block<'a, C>
+where
+    C: Classes,
+{
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, C> DerefMut for Block<'a, C>
+where
+    C: Classes,
+{
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, C> Drop for Block<'a, C>
+where
+    C: Classes,
+{
+    fn drop(&mut self) {
+        unsafe { release(self.classes, self.ptr) }
+    }
+}
+
+unsafe impl<'a, C> Send for Block<'a, C> where C: Classes + Sync {}
+
+unsafe impl<'a, C> Sync for Block<'a, C> where C: Classes + Sync {}
+
+impl<'a, C> fmt::Debug for Block<'a, C>
+where
+    C: Classes,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <[u8] as fmt::Debug>::fmt(self, f)
+    }
+}
+
+// NOTE the functions below are used by the `Classes` implementations
+
+pub(crate) fn block_size<A>() -> usize {
+    mem::size_of::<A>()
+}
+
+// Claims a block from `pool` and tags it with the index of its size class
+pub(crate) fn alloc<A>(pool: &Pool<SizeClass<A>>, class: usize) -> Option<NonNull<u8>> {
+    // the class index must be right before the bytes; see `HEADER`
+    assert!(mem::align_of::<A>() <= mem::align_of::<usize>());
+
+    let node = pool.alloc()?.node;
+
+    unsafe {
+        let block = node.as_ref().data.get();
+        ptr::write(&mut (*block).class, class);
+
+        Some(NonNull::new_unchecked(
+            &mut (*block).bytes as *mut A as *mut u8,
+        ))
+    }
+}
+
+// Returns a block claimed with `alloc` to `pool`
+pub(crate) unsafe fn free<A>(pool: &Pool<SizeClass<A>>, ptr: NonNull<u8>) {
+    pool.dealloc(NonNull::new_unchecked(
+        ptr.as_ptr().sub(HEADER) as *mut Node<SizeClass<A>>
+    ))
+}
+
+// Returns the size class of a block claimed with `alloc`
+unsafe fn class(ptr: NonNull<u8>) -> usize {
+    *(ptr.as_ptr().sub(HEADER) as *const usize)
+}
+
+unsafe fn release<C>(classes: &C, ptr: NonNull<u8>)
+where
+    C: Classes,
+{
+    classes.free(class(ptr), ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{alloc::GlobalAlloc, alloc::Layout, mem::MaybeUninit};
+
+    use super::{SizeClass, SizeClasses};
+    use crate::pool::{Node, Pool};
+
+    #[test]
+    fn smallest_fit() {
+        static A: SizeClasses<(Pool<SizeClass<[u8; 8]>>, Pool<SizeClass<[u8; 32]>>)> =
+            SizeClasses::new((Pool::new(), Pool::new()));
+
+        static mut SMALL: MaybeUninit<[Node<SizeClass<[u8; 8]>>; 1]> = MaybeUninit::uninit();
+        static mut LARGE: MaybeUninit<[Node<SizeClass<[u8; 32]>>; 1]> = MaybeUninit::uninit();
+
+        A.classes().0.grow_exact(unsafe { &mut SMALL });
+        A.classes().1.grow_exact(unsafe { &mut LARGE });
+        assert_eq!(A.max_size(), 32);
+
+        // too large
+        assert!(A.alloc(33).is_none());
+
+        let mut x = A.alloc(4).unwrap();
+        assert_eq!(x.capacity(), 8);
+        assert_eq!(*x, [0; 4]);
+        x.copy_from_slice(&[1, 2, 3, 4]);
+
+        // the small class is exhausted; falls back to the large one
+        let y = A.alloc(2).unwrap();
+        assert_eq!(y.capacity(), 32);
+
+        // everything is exhausted
+        assert!(A.alloc(1).is_none());
+
+        drop(x);
+
+        // the block was returned to its own class
+        let x = A.alloc(8).unwrap();
+        assert_eq!(x.capacity(), 8);
+        assert_eq!(*x, [0; 8]);
+        drop(y);
+        assert!(A.alloc(16).is_some());
+    }
+
+    #[test]
+    fn global_alloc() {
+        static A: SizeClasses<(Pool<SizeClass<[u8; 8]>>, Pool<SizeClass<[u8; 32]>>)> =
+            SizeClasses::new((Pool::new(), Pool::new()));
+
+        static mut SMALL: MaybeUninit<[Node<SizeClass<[u8; 8]>>; 2]> = MaybeUninit::uninit();
+        static mut LARGE: MaybeUninit<[Node<SizeClass<[u8; 32]>>; 1]> = MaybeUninit::uninit();
+
+        A.classes().0.grow_exact(unsafe { &mut SMALL });
+        A.classes().1.grow_exact(unsafe { &mut LARGE });
+
+        unsafe {
+            // over-aligned requests can't be served
+            assert!(GlobalAlloc::alloc(&A, Layout::from_size_align(8, 64).unwrap()).is_null());
+
+            let layout = Layout::new::<u32>();
+            let p = GlobalAlloc::alloc(&A, layout);
+            assert!(!p.is_null());
+            assert_eq!(p as usize % layout.align(), 0);
+            (p as *mut u32).write(42);
+
+            // fits in the same block
+            let q = A.realloc(p, layout, 8);
+            assert_eq!(q, p);
+
+            // moves to a larger block
+            let r = A.realloc(q, Layout::from_size_align(8, 4).unwrap(), 24);
+            assert_ne!(r, q);
+            assert_eq!((r as *mut u32).read(), 42);
+
+            // nothing large enough is left
+            assert!(A
+                .realloc(r, Layout::from_size_align(24, 4).unwrap(), 64)
+                .is_null());
+
+            A.dealloc(r, Layout::from_size_align(24, 4).unwrap());
+            assert!(!GlobalAlloc::alloc(&A, Layout::from_size_align(32, 8).unwrap()).is_null());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Usage statistics of a `Pool`
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A snapshot of the usage statistics of a memory pool
+///
+/// The counters are updated independently so, if the pool is used concurrently, a snapshot may not
+/// reflect the state of the pool at a single point in time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Stats {
+    /// The total number of blocks that have been added to the pool with `grow` / `grow_exact`
+    pub capacity: usize,
+
+    /// The number of blocks that can currently be allocated
+    pub free: usize,
+
+    /// The number of blocks that are currently allocated
+    pub in_use: usize,
+
+    /// The smallest number of free blocks the pool has had, relative to its current capacity;
+    /// i.e. `capacity` minus the largest number of blocks that have been in use at the same time
+    pub low_water_mark: usize,
+
+    /// The number of `alloc` calls that returned `None` because the pool was exhausted
+    pub failed_allocs: usize,
+}
+
+pub(crate) struct Counters {
+    capacity: AtomicUsize,
+    in_use: AtomicUsize,
+    peak_in_use: AtomicUsize,
+    failed_allocs: AtomicUsize,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Counters {
+            capacity: AtomicUsize::new(0),
+            in_use: AtomicUsize::new(0),
+            peak_in_use: AtomicUsize::new(0),
+            failed_allocs: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn grow(&self, n: usize) {
+        add(&self.capacity, n);
+    }
+
+    pub(crate) fn alloc(&self) {
+        let in_use = add(&self.in_use, 1);
+        max(&self.peak_in_use, in_use);
+    }
+
+    pub(crate) fn dealloc(&self) {
+        sub(&self.in_use, 1);
+    }
+
+    pub(crate) fn alloc_failed(&self) {
+        add(&self.failed_allocs, 1);
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let in_use = self.in_use.load(Ordering::Relaxed);
+        let peak_in_use = self.peak_in_use.load(Ordering::Relaxed);
+
+        Stats {
+            capacity,
+            free: capacity.saturating_sub(in_use),
+            in_use,
+            low_water_mark: capacity.saturating_sub(peak_in_use),
+            failed_allocs: self.failed_allocs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// NOTE the counters don't synchronize any other memory so `Relaxed` is enough
+
+// Returns the new value
+#[cfg(not(no_cas))]
+fn add(counter: &AtomicUsize, n: usize) -> usize {
+    counter.fetch_add(n, Ordering::Relaxed).wrapping_add(n)
+}
+
+#[cfg(not(no_cas))]
+fn sub(counter: &AtomicUsize, n: usize) {
+    counter.fetch_sub(n, Ordering::Relaxed);
+}
+
+#[cfg(not(no_cas))]
+fn max(counter: &AtomicUsize, val: usize) {
+    let mut current = counter.load(Ordering::Relaxed);
+    while val > current {
+        match counter.compare_exchange_weak(current, val, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(c) => current = c,
+        }
+    }
+}
+
+#[cfg(no_cas)]
+fn add(counter: &AtomicUsize, n: usize) -> usize {
+    crate::critical_section::free(|| {
+        let new = counter.load(Ordering::Relaxed).wrapping_add(n);
+        counter.store(new, Ordering::Relaxed);
+        new
+    })
+}
+
+#[cfg(no_cas)]
+fn sub(counter: &AtomicUsize, n: usize) {
+    crate::critical_section::free(|| {
+        counter.store(
+            counter.load(Ordering::Relaxed).wrapping_sub(n),
+            Ordering::Relaxed,
+        )
+    })
+}
+
+#[cfg(no_cas)]
+fn max(counter: &AtomicUsize, val: usize) {
+    crate::critical_section::free(|| {
+        if val > counter.load(Ordering::Relaxed) {
+            counter.store(val, Ordering::Relaxed)
+        }
+    })
+}
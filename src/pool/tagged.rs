@@ -0,0 +1,149 @@
+//! ABA-safe head pointer for the Treiber stack on x86_64 and AArch64
+//!
+//! The LL/SC argument that makes the stack ABA-safe on Cortex-M doesn't hold on these multi-core
+//! architectures: other cores can pop and push nodes between the load of the head and the CAS
+//! operation. Instead, the head pointer is packed with a tag into a 64-bit word that's updated with
+//! a single CAS operation. The tag is incremented every time the head changes so a `pop` operation
+//! that raced with other `pop` / `push` operations will fail its CAS, even if the head node is the
+//! same, because the tag will be different.
+//!
+//! To make room for the tag the pointer is stored as a 32-bit offset from an "anchor" `static`
+//! variable. Thus the nodes must be within +/-2 GiB of the static data of the program; any memory
+//! given to `grow` that's placed in a `static` variable meets this requirement.
+
+use core::{
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// An atomic `TaggedPtr`
+pub(crate) struct AtomicTaggedPtr<T> {
+    inner: AtomicU64,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> AtomicTaggedPtr<T> {
+    pub(crate) const fn null() -> Self {
+        AtomicTaggedPtr {
+            inner: AtomicU64::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn load(&self, order: Ordering) -> TaggedPtr<T> {
+        TaggedPtr {
+            inner: self.inner.load(order),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn compare_exchange_weak(
+        &self,
+        current: TaggedPtr<T>,
+        new: TaggedPtr<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(), TaggedPtr<T>> {
+        self.inner
+            .compare_exchange_weak(current.inner, new.inner, success, failure)
+            .map(drop)
+            .map_err(|inner| TaggedPtr {
+                inner,
+                _marker: PhantomData,
+            })
+    }
+}
+
+/// A pointer packed with a 32-bit tag
+///
+/// The lower half is the offset of the pointer from the anchor; an offset of zero represents the
+/// null pointer. The upper half is the tag.
+pub(crate) struct TaggedPtr<T> {
+    inner: u64,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> Clone for TaggedPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaggedPtr<T> {}
+
+impl<T> TaggedPtr<T> {
+    /// Returns `None` if `ptr` is too far away from the anchor
+    pub(crate) fn new(ptr: *mut T, tag: u32) -> Option<Self> {
+        let offset = if ptr.is_null() {
+            0
+        } else {
+            let offset = (ptr as isize).wrapping_sub(anchor());
+
+            // NOTE an offset of zero is reserved for the null pointer
+            if offset == 0
+                || offset > i32::max_value() as isize
+                || offset < i32::min_value() as isize
+            {
+                return None;
+            }
+
+            offset as i32
+        };
+
+        Some(TaggedPtr {
+            inner: u64::from(tag) << 32 | u64::from(offset as u32),
+            _marker: PhantomData,
+        })
+    }
+
+    pub(crate) fn as_ptr(self) -> *mut T {
+        let offset = self.inner as u32 as i32;
+
+        if offset == 0 {
+            ptr::null_mut()
+        } else {
+            anchor().wrapping_add(offset as isize) as *mut T
+        }
+    }
+
+    pub(crate) fn tag(self) -> u32 {
+        (self.inner >> 32) as u32
+    }
+}
+
+fn anchor() -> isize {
+    static ANCHOR: u8 = 0;
+
+    &ANCHOR as *const u8 as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use super::{anchor, TaggedPtr};
+
+    #[test]
+    fn round_trip() {
+        static mut X: u32 = 0;
+
+        let p = unsafe { &mut X as *mut u32 };
+        let tagged = TaggedPtr::new(p, u32::max_value()).unwrap();
+        assert_eq!(tagged.as_ptr(), p);
+        assert_eq!(tagged.tag(), u32::max_value());
+
+        let null = TaggedPtr::<u32>::new(ptr::null_mut(), 1).unwrap();
+        assert!(null.as_ptr().is_null());
+        assert_eq!(null.tag(), 1);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let far = anchor().wrapping_add(1 << 32) as *mut u32;
+        assert!(TaggedPtr::new(far, 0).is_none());
+
+        let far = anchor().wrapping_sub(1 << 32) as *mut u32;
+        assert!(TaggedPtr::new(far, 0).is_none());
+    }
+}
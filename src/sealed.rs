@@ -2,8 +2,21 @@
 pub mod spsc {
 
 use core::sync::atomic::{self, AtomicU16, AtomicU8, AtomicUsize, Ordering};
+use generic_array::typenum::Unsigned;
 use crate::spsc::{MultiCore, SingleCore};
 
+/// The capacity of a `spsc::Queue`; implemented for all type level integers
+pub trait Capacity: Unsigned {
+    // NOTE a capacity of zero is treated as a power of two; no slot is ever computed in that case
+    #[doc(hidden)]
+    const POWER_OF_TWO: bool = Self::USIZE & Self::USIZE.wrapping_sub(1) == 0;
+
+    #[doc(hidden)]
+    const MASK: usize = Self::USIZE.wrapping_sub(1);
+}
+
+impl<N> Capacity for N where N: Unsigned {}
+
 pub unsafe trait XCore {
     fn is_multi_core() -> bool;
 }
@@ -20,7 +33,7 @@ unsafe impl XCore for MultiCore {
     }
 }
 
-pub unsafe trait Uxx: Into<usize> + Send {
+pub unsafe trait Uxx: Copy + Into<usize> + Send {
     #[doc(hidden)]
     fn truncate(x: usize) -> Self;
 
@@ -159,24 +172,407 @@ unsafe impl Uxx for usize {
 pub mod binary_heap {
 
 use core::cmp::Ordering;
-use crate::binary_heap::{Min, Max};
+use crate::binary_heap::{Compare, Max, MaxBy, Min, MinBy, Stable};
 
 /// The binary heap kind: min-heap or max-heap
-pub unsafe trait Kind {
+pub unsafe trait Kind<T> {
     #[doc(hidden)]
     fn ordering() -> Ordering;
+
+    #[doc(hidden)]
+    fn cmp(a: &T, b: &T) -> Ordering;
+}
+
+unsafe impl<T> Kind<T> for Min
+where
+    T: Ord,
+{
+    fn ordering() -> Ordering {
+        Ordering::Less
+    }
+
+    fn cmp(a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+unsafe impl<T> Kind<T> for Max
+where
+    T: Ord,
+{
+    fn ordering() -> Ordering {
+        Ordering::Greater
+    }
+
+    fn cmp(a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
 }
 
-unsafe impl Kind for Min {
+unsafe impl<T, C> Kind<T> for MinBy<C>
+where
+    C: Compare<T>,
+{
     fn ordering() -> Ordering {
         Ordering::Less
     }
+
+    fn cmp(a: &T, b: &T) -> Ordering {
+        C::compare(a, b)
+    }
 }
 
-unsafe impl Kind for Max {
+unsafe impl<T, C> Kind<T> for MaxBy<C>
+where
+    C: Compare<T>,
+{
     fn ordering() -> Ordering {
         Ordering::Greater
     }
+
+    fn cmp(a: &T, b: &T) -> Ordering {
+        C::compare(a, b)
+    }
+}
+
+// NOTE items that compare as equal are ordered by their sequence number; the item that was pushed
+// first goes to the top of the heap
+unsafe impl<T, K> Kind<(T, u32)> for Stable<K>
+where
+    K: Kind<T>,
+{
+    fn ordering() -> Ordering {
+        K::ordering()
+    }
+
+    fn cmp(a: &(T, u32), b: &(T, u32)) -> Ordering {
+        K::cmp(&a.0, &b.0).then_with(|| {
+            if K::ordering() == Ordering::Greater {
+                b.1.cmp(&a.1)
+            } else {
+                a.1.cmp(&b.1)
+            }
+        })
+    }
+}
+
+/// Tick counter type of a `TimerQueue`
+pub unsafe trait Tick: Copy {
+    /// Compares `a` against `b` taking into account that the counter may have wrapped around
+    /// between them
+    #[doc(hidden)]
+    fn wrapping_cmp(a: Self, b: Self) -> Ordering;
+}
+
+unsafe impl Tick for u32 {
+    fn wrapping_cmp(a: Self, b: Self) -> Ordering {
+        (a.wrapping_sub(b) as i32).cmp(&0)
+    }
+}
+
+unsafe impl Tick for u64 {
+    fn wrapping_cmp(a: Self, b: Self) -> Ordering {
+        (a.wrapping_sub(b) as i64).cmp(&0)
+    }
+}
+
 }
 
+/// Sealed traits and implementations for `mpmc`
+pub mod mpmc {
+
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU16, AtomicU8, AtomicUsize, Ordering},
+};
+use generic_array::typenum::{consts::*, UInt, Unsigned, B0};
+
+use crate::cache_padded::CachePadded;
+
+/// Position / sequence number type of a `mpmc::Queue`
+pub unsafe trait Uxx: Copy + Eq {
+    #[doc(hidden)]
+    const ZERO: Self;
+
+    #[doc(hidden)]
+    const ONE: Self;
+
+    #[doc(hidden)]
+    const TWO: Self;
+
+    #[doc(hidden)]
+    fn truncate(x: usize) -> Self;
+
+    #[doc(hidden)]
+    fn to_usize(self) -> usize;
+
+    #[doc(hidden)]
+    fn wrapping_add(self, other: Self) -> Self;
+
+    #[doc(hidden)]
+    fn wrapping_sub(self, other: Self) -> Self;
+
+    /// Signed distance from `b` to `a` taking into account that the counters may have wrapped
+    /// around between them
+    #[doc(hidden)]
+    fn dif(a: Self, b: Self) -> isize;
+
+    #[doc(hidden)]
+    unsafe fn load(x: *const Self, order: Ordering) -> Self;
+
+    #[doc(hidden)]
+    unsafe fn store(x: *const Self, val: Self, order: Ordering);
+
+    #[doc(hidden)]
+    unsafe fn compare_exchange_weak(
+        x: *const Self,
+        current: Self,
+        new: Self,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self, Self>;
+}
+
+macro_rules! uxx {
+    ($($uxx:ident, $ixx:ident, $atomic:ident;)+) => {
+        $(
+            unsafe impl Uxx for $uxx {
+                const ZERO: Self = 0;
+
+                const ONE: Self = 1;
+
+                const TWO: Self = 2;
+
+                fn truncate(x: usize) -> Self {
+                    x as $uxx
+                }
+
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+
+                fn wrapping_add(self, other: Self) -> Self {
+                    $uxx::wrapping_add(self, other)
+                }
+
+                fn wrapping_sub(self, other: Self) -> Self {
+                    $uxx::wrapping_sub(self, other)
+                }
+
+                fn dif(a: Self, b: Self) -> isize {
+                    a.wrapping_sub(b) as $ixx as isize
+                }
+
+                unsafe fn load(x: *const Self, order: Ordering) -> Self {
+                    (*(x as *const $atomic)).load(order)
+                }
+
+                unsafe fn store(x: *const Self, val: Self, order: Ordering) {
+                    (*(x as *const $atomic)).store(val, order)
+                }
+
+                #[cfg(not(no_cas))]
+                unsafe fn compare_exchange_weak(
+                    x: *const Self,
+                    current: Self,
+                    new: Self,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> Result<Self, Self> {
+                    (*(x as *const $atomic)).compare_exchange_weak(current, new, success, failure)
+                }
+
+                // NOTE the critical section is also a compiler barrier and these targets are
+                // single core so `Relaxed` accesses suffice
+                #[cfg(no_cas)]
+                unsafe fn compare_exchange_weak(
+                    x: *const Self,
+                    current: Self,
+                    new: Self,
+                    _: Ordering,
+                    _: Ordering,
+                ) -> Result<Self, Self> {
+                    let x = &*(x as *const $atomic);
+                    crate::critical_section::free(|| {
+                        let old = x.load(Ordering::Relaxed);
+                        if old == current {
+                            x.store(new, Ordering::Relaxed);
+                            Ok(old)
+                        } else {
+                            Err(old)
+                        }
+                    })
+                }
+            }
+        )+
+    };
+}
+
+uxx! {
+    u8, i8, AtomicU8;
+    u16, i16, AtomicU16;
+    usize, isize, AtomicUsize;
+}
+
+/// A slot of a `mpmc::Queue`
+pub struct Cell<T, I> {
+    pub(crate) data: MaybeUninit<T>,
+    // NOTE this is the sequence number minus the position of the cell in the first lap so that an
+    // all-zeros buffer is a valid initial state. Positions advance in steps of 2; the low bit is
+    // set when the cell was published without an item (see `mpmc::Reservation`'s destructor).
+    // Every cell's sequence number sits on its own cache line when the `cache-padded` feature is
+    // enabled
+    pub(crate) sequence: CachePadded<UnsafeCell<I>>,
+}
+
+pub unsafe trait Init {
+    #[doc(hidden)]
+    const INIT: Self;
+}
+
+unsafe impl<T, I> Init for Cell<T, I>
+where
+    I: Uxx,
+{
+    const INIT: Self = Cell {
+        data: MaybeUninit::uninit(),
+        sequence: CachePadded::new(UnsafeCell::new(I::ZERO)),
+    };
+}
+
+/// An array of `Self` elements of type `C`; only implemented for powers of two
+pub unsafe trait Array<C> {
+    #[doc(hidden)]
+    type Array;
+
+    #[doc(hidden)]
+    const INIT: Self::Array;
+}
+
+unsafe impl<C> Array<C> for U1
+where
+    C: Init,
+{
+    type Array = [C; 1];
+
+    const INIT: [C; 1] = [C::INIT];
+}
+
+unsafe impl<C, N> Array<C> for UInt<N, B0>
+where
+    N: Array<C>,
+{
+    type Array = [N::Array; 2];
+
+    const INIT: [N::Array; 2] = [N::INIT, N::INIT];
+}
+
+/// The capacity of a `mpmc::Queue`: a power of two greater than one
+pub unsafe trait Capacity<T>: Unsigned {
+    #[doc(hidden)]
+    type Index: Uxx;
+
+    #[doc(hidden)]
+    type Cells;
+
+    #[doc(hidden)]
+    const CELLS: Self::Cells;
+}
+
+// NOTE the positions advance in steps of 2 and `Uxx::dif` must be able to represent `+2 * N` so
+// the largest capacity an `uN` index can handle is `2^(N - 3)`. A capacity of one is not supported
+// because the algorithm can't tell apart a full cell from an empty one in that case
+macro_rules! capacity {
+    ($index:ty; $($N:ident),+) => {
+        $(
+            unsafe impl<T> Capacity<T> for $N {
+                type Index = $index;
+
+                type Cells = <$N as Array<Cell<T, $index>>>::Array;
+
+                const CELLS: Self::Cells = <$N as Array<Cell<T, $index>>>::INIT;
+            }
+        )+
+    };
+}
+
+capacity!(u8; U2, U4, U8, U16, U32);
+capacity!(u16; U64, U128, U256, U512, U1024, U2048, U4096, U8192);
+capacity!(
+    usize;
+    U16384, U32768, U65536, U131072, U262144, U524288, U1048576, U2097152, U4194304, U8388608,
+    U16777216
+);
+
+}
+
+/// Sealed traits and implementations for `pool`
+pub mod pool {
+
+use core::ptr::NonNull;
+
+use as_slice::AsMutSlice;
+use crate::pool::{size_class::{self, SizeClass}, Pool};
+
+/// The size classes of a `SizeClasses` allocator: a tuple of 1 to 8 `Pool<SizeClass<A>>`s
+pub unsafe trait Classes {
+    #[doc(hidden)]
+    const LEN: usize;
+
+    #[doc(hidden)]
+    fn block_size(&self, class: usize) -> usize;
+
+    #[doc(hidden)]
+    fn alloc(&self, class: usize) -> Option<NonNull<u8>>;
+
+    #[doc(hidden)]
+    unsafe fn free(&self, class: usize, ptr: NonNull<u8>);
+}
+
+macro_rules! classes {
+    ($($LEN:expr => ($($A:ident: $i:tt),+);)+) => {
+        $(
+            unsafe impl<$($A),+> Classes for ($(Pool<SizeClass<$A>>,)+)
+            where
+                $($A: AsMutSlice<Element = u8>,)+
+            {
+                const LEN: usize = $LEN;
+
+                fn block_size(&self, class: usize) -> usize {
+                    match class {
+                        $($i => size_class::block_size::<$A>(),)+
+                        _ => 0,
+                    }
+                }
+
+                fn alloc(&self, class: usize) -> Option<NonNull<u8>> {
+                    match class {
+                        $($i => size_class::alloc(&self.$i, class),)+
+                        _ => None,
+                    }
+                }
+
+                unsafe fn free(&self, class: usize, ptr: NonNull<u8>) {
+                    match class {
+                        $($i => size_class::free(&self.$i, ptr),)+
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+classes!(
+    1 => (A: 0);
+    2 => (A: 0, B: 1);
+    3 => (A: 0, B: 1, C: 2);
+    4 => (A: 0, B: 1, C: 2, D: 3);
+    5 => (A: 0, B: 1, C: 2, D: 3, E: 4);
+    6 => (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+    7 => (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+    8 => (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+);
+
 }
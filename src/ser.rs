@@ -12,9 +12,9 @@ use crate::{
 
 impl<T, N, KIND> Serialize for BinaryHeap<T, N, KIND>
 where
-    T: Ord + Serialize,
+    T: Serialize,
     N: ArrayLength<T>,
-    KIND: BinaryHeapKind,
+    KIND: BinaryHeapKind<T>,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -0,0 +1,616 @@
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    iter::FromIterator,
+    mem,
+    ops::{self, Bound, RangeBounds},
+    ptr,
+};
+
+use generic_array::{ArrayLength, GenericArray};
+
+use crate::linear_map::{IntoIter, Iter, IterMut};
+
+/// A fixed capacity map / dictionary that keeps its entries sorted by key
+///
+/// Lookups are performed via binary search so they are **O(log N)**. Insertions and removals need
+/// to shift the entries that come after the affected key, which makes them **O(N)**. Iteration
+/// visits the entries in ascending key order.
+///
+/// Like [`LinearMap`](struct.LinearMap.html) the entries are stored in a `Vec<(K, V), N>`.
+pub struct SortedLinearMap<K, V, N>(
+    #[doc(hidden)] pub crate::i::SortedLinearMap<GenericArray<(K, V), N>>,
+)
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord;
+
+impl<A> crate::i::SortedLinearMap<A> {
+    /// `SortedLinearMap` `const` constructor; wrap the returned value in
+    /// [`SortedLinearMap`](../struct.SortedLinearMap.html)
+    pub const fn new() -> Self {
+        Self {
+            buffer: crate::i::Vec::new(),
+        }
+    }
+}
+
+impl<K, V, N> SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    /// Creates an empty `SortedLinearMap`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// // allocate the map on the stack
+    /// let mut map: SortedLinearMap<&str, isize, U8> = SortedLinearMap::new();
+    ///
+    /// // allocate the map in a static variable
+    /// static mut MAP: SortedLinearMap<&str, isize, U8> =
+    ///     SortedLinearMap(heapless::i::SortedLinearMap::new());
+    /// ```
+    pub fn new() -> Self {
+        SortedLinearMap(crate::i::SortedLinearMap::new())
+    }
+
+    /// Returns the number of elements that the map can hold
+    ///
+    /// Computes in **O(1)** time
+    pub fn capacity(&self) -> usize {
+        N::to_usize()
+    }
+
+    /// Clears the map, removing all key-value pairs
+    pub fn clear(&mut self) {
+        self.0.buffer.clear()
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// Computes in **O(log N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_ok()
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    ///
+    /// Computes in **O(log N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.find(key) {
+            Ok(idx) => Some(unsafe { &self.0.buffer.as_slice().get_unchecked(idx).1 }),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key
+    ///
+    /// Computes in **O(log N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// if let Some(x) = map.get_mut(&1) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// ```
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.find(key) {
+            Ok(idx) => Some(unsafe { &mut self.0.buffer.as_mut_slice().get_unchecked_mut(idx).1 }),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the number of elements in this map
+    ///
+    /// Computes in **O(1)** time
+    pub fn len(&self) -> usize {
+        self.0.buffer.len
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, `None` is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old value is returned.
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// assert_eq!(map.insert(37, "a").unwrap(), None);
+    /// assert_eq!(map.is_empty(), false);
+    ///
+    /// map.insert(37, "b").unwrap();
+    /// assert_eq!(map.insert(37, "c").unwrap(), Some("b"));
+    /// assert_eq!(map[&37], "c");
+    /// ```
+    pub fn insert(&mut self, key: K, mut value: V) -> Result<Option<V>, (K, V)> {
+        match self.find(&key) {
+            Ok(idx) => {
+                let v = unsafe { &mut self.0.buffer.as_mut_slice().get_unchecked_mut(idx).1 };
+                mem::swap(v, &mut value);
+                Ok(Some(value))
+            }
+            Err(idx) => {
+                self.0.buffer.insert(idx, (key, value))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns true if the map contains no elements
+    ///
+    /// Computes in **O(1)** time
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// An iterator visiting all key-value pairs in ascending key order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert("c", 3).unwrap();
+    /// map.insert("a", 1).unwrap();
+    /// map.insert("b", 2).unwrap();
+    ///
+    /// let mut iter = map.iter();
+    /// assert_eq!(iter.next(), Some((&"a", &1)));
+    /// assert_eq!(iter.next(), Some((&"b", &2)));
+    /// assert_eq!(iter.next(), Some((&"c", &3)));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            iter: self.0.buffer.as_slice().iter(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in ascending key order, with mutable references to
+    /// the values
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            iter: self.0.buffer.as_mut_slice().iter_mut(),
+        }
+    }
+
+    /// An iterator visiting all keys in ascending order
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// An iterator visiting all values in ascending key order
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// An iterator visiting all values mutably in ascending key order
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    /// An iterator over the key-value pairs whose keys fall within `range`, in ascending key order
+    ///
+    /// Computes in **O(log N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert(3, "c").unwrap();
+    /// map.insert(5, "e").unwrap();
+    /// map.insert(8, "h").unwrap();
+    /// map.insert(1, "a").unwrap();
+    ///
+    /// let mut iter = map.range(2..=5);
+    /// assert_eq!(iter.next(), Some((&3, &"c")));
+    /// assert_eq!(iter.next(), Some((&5, &"e")));
+    /// assert_eq!(iter.next(), None);
+    ///
+    /// assert_eq!(map.range(6..).next(), Some((&8, &"h")));
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.partition_point(|k| k.borrow() < start),
+            Bound::Excluded(start) => self.partition_point(|k| k.borrow() <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.partition_point(|k| k.borrow() <= end),
+            Bound::Excluded(end) => self.partition_point(|k| k.borrow() < end),
+            Bound::Unbounded => self.len(),
+        };
+
+        let entries = self.0.buffer.as_slice();
+        Iter {
+            iter: entries[start..end.max(start)].iter(),
+        }
+    }
+
+    /// Returns the key-value pair with the smallest key, or `None` if the map is empty
+    ///
+    /// Computes in **O(1)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// assert_eq!(map.first_key_value(), None);
+    /// map.insert(2, "b").unwrap();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.first_key_value(), Some((&1, &"a")));
+    /// ```
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Returns the key-value pair with the greatest key, or `None` if the map is empty
+    ///
+    /// Computes in **O(1)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// assert_eq!(map.last_key_value(), None);
+    /// map.insert(2, "b").unwrap();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.last_key_value(), Some((&2, &"b")));
+    /// ```
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in the
+    /// map
+    ///
+    /// The order of the remaining entries is preserved.
+    ///
+    /// Computes in **O(N)** time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::SortedLinearMap;
+    /// use heapless::consts::*;
+    ///
+    /// let mut map: SortedLinearMap<_, _, U8> = SortedLinearMap::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert_eq!(map.remove(&1), Some("a"));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.find(key) {
+            Ok(idx) => Some(self.0.buffer.remove(idx).1),
+            Err(_) => None,
+        }
+    }
+
+    // Returns the index of `key` or the index where it should be inserted
+    fn find<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0
+            .buffer
+            .as_slice()
+            .binary_search_by(|(k, _)| k.borrow().cmp(key))
+    }
+
+    // Returns the number of leading entries whose keys satisfy `pred`. `pred` must be `true` for a
+    // (possibly empty) prefix of the entries and `false` for the rest
+    fn partition_point<P>(&self, mut pred: P) -> usize
+    where
+        P: FnMut(&K) -> bool,
+    {
+        let entries = self.0.buffer.as_slice();
+
+        let (mut lo, mut hi) = (0, entries.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(unsafe { &entries.get_unchecked(mid).0 }) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+impl<'a, K, V, N, Q> ops::Index<&'a Q> for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, K, V, N, Q> ops::IndexMut<&'a Q> for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+{
+    fn index_mut(&mut self, key: &Q) -> &mut V {
+        self.get_mut(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V, N> Default for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, N> Clone for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord + Clone,
+    V: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(crate::i::SortedLinearMap {
+            buffer: self.0.buffer.clone(),
+        })
+    }
+}
+
+impl<K, V, N> fmt::Debug for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, N> FromIterator<(K, V)> for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut out = Self::new();
+        for (k, v) in iter {
+            out.insert(k, v)
+                .ok()
+                .expect("SortedLinearMap::from_iter overflow");
+        }
+        out
+    }
+}
+
+impl<K, V, N> IntoIterator for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let buffer = unsafe { ptr::read(&self.0.buffer) };
+        mem::forget(self);
+
+        Self::IntoIter {
+            inner: crate::Vec(buffer).into_iter(),
+        }
+    }
+}
+
+impl<'a, K, V, N> IntoIterator for &'a SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, N> Drop for SortedLinearMap<K, V, N>
+where
+    N: ArrayLength<(K, V)>,
+    K: Ord,
+{
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0.buffer.as_mut_slice()) }
+    }
+}
+
+impl<K, V, N, N2> PartialEq<SortedLinearMap<K, V, N2>> for SortedLinearMap<K, V, N>
+where
+    K: Ord,
+    V: PartialEq,
+    N: ArrayLength<(K, V)>,
+    N2: ArrayLength<(K, V)>,
+{
+    fn eq(&self, other: &SortedLinearMap<K, V, N2>) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|((k1, v1), (k2, v2))| k1.cmp(k2) == Ordering::Equal && v1 == v2)
+    }
+}
+
+impl<K, V, N> Eq for SortedLinearMap<K, V, N>
+where
+    K: Ord,
+    V: Eq,
+    N: ArrayLength<(K, V)>,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec::Vec;
+
+    use crate::{consts::*, SortedLinearMap};
+
+    #[test]
+    fn static_new() {
+        static mut _L: SortedLinearMap<i32, i32, U8> =
+            SortedLinearMap(crate::i::SortedLinearMap::new());
+    }
+
+    #[test]
+    fn ordered() {
+        let mut map = SortedLinearMap::<_, _, U8>::new();
+        for &k in &[5, 1, 7, 3, 2] {
+            assert_eq!(map.insert(k, k * 10).unwrap(), None);
+        }
+        assert_eq!(map.keys().cloned().collect::<Vec<_>>(), [1, 2, 3, 5, 7]);
+
+        assert_eq!(map.remove(&3), Some(30));
+        assert_eq!(map.remove(&3), None);
+        assert_eq!(map.keys().cloned().collect::<Vec<_>>(), [1, 2, 5, 7]);
+
+        assert_eq!(map.first_key_value(), Some((&1, &10)));
+        assert_eq!(map.last_key_value(), Some((&7, &70)));
+    }
+
+    #[test]
+    fn range() {
+        let map = [(1, 'a'), (3, 'c'), (5, 'e'), (7, 'g')]
+            .iter()
+            .cloned()
+            .collect::<SortedLinearMap<_, _, U8>>();
+
+        let keys = |iter: crate::linear_map::Iter<'_, i32, char>| {
+            iter.map(|(k, _)| *k).collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(map.range(..)), [1, 3, 5, 7]);
+        assert_eq!(keys(map.range(3..7)), [3, 5]);
+        assert_eq!(keys(map.range(2..=7)), [3, 5, 7]);
+        assert_eq!(keys(map.range(..=1)), [1]);
+        assert_eq!(keys(map.range(8..)), []);
+        assert_eq!(keys(map.range(6..2)), []);
+        assert_eq!(
+            map.range(3..7).rev().map(|(k, _)| *k).collect::<Vec<_>>(),
+            [5, 3]
+        );
+    }
+
+    #[test]
+    fn full() {
+        let mut map = SortedLinearMap::<_, _, U2>::new();
+        map.insert(2, ()).unwrap();
+        map.insert(1, ()).unwrap();
+        assert_eq!(map.insert(1, ()).unwrap(), Some(()));
+        assert_eq!(map.insert(0, ()), Err((0, ())));
+    }
+
+    #[test]
+    fn partial_eq() {
+        let mut a = SortedLinearMap::<_, _, U2>::new();
+        a.insert("k1", "v1").unwrap();
+        a.insert("k2", "v2").unwrap();
+
+        let mut b = SortedLinearMap::<_, _, U4>::new();
+        b.insert("k2", "v2").unwrap();
+        b.insert("k1", "v1").unwrap();
+
+        assert!(a == b);
+
+        b.insert("k3", "v3").unwrap();
+
+        assert!(a != b);
+    }
+}
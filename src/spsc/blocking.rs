@@ -0,0 +1,166 @@
+use std::{
+    task::Waker,
+    thread,
+    time::{Duration, Instant},
+};
+
+use generic_array::{typenum::PowerOfTwo, ArrayLength};
+
+use crate::{
+    sealed::spsc as sealed,
+    spsc::{thread_waker::thread_waker, Consumer, Producer},
+};
+
+thread_local! {
+    // a waker that unparks the current thread
+    static WAKER: Waker = thread_waker();
+}
+
+// Retries `op` until it succeeds or the `deadline` is reached, parking the current thread between
+// attempts; `register` must register the given waker to be woken up by the other end point
+fn block<R>(
+    deadline: Option<Instant>,
+    register: impl Fn(&Waker),
+    mut op: impl FnMut() -> Option<R>,
+) -> Option<R> {
+    loop {
+        if let Some(r) = op() {
+            return Some(r);
+        }
+
+        WAKER.with(|waker| register(waker));
+
+        // the other end point may have made progress before we registered the waker
+        if let Some(r) = op() {
+            return Some(r);
+        }
+
+        match deadline {
+            None => thread::park(),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                thread::park_timeout(deadline - now);
+            }
+        }
+    }
+}
+
+// NOTE see `spsc::Queue`'s `impl_!` for why `$bound` is needed
+macro_rules! impl_ {
+    ($uxx:ident $(, $bound:ident)?) => {
+        impl<'a, T, N, C> Producer<'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Adds an `item` to the end of the queue, blocking the current thread until there's
+            /// room for it
+            pub fn enqueue_blocking(&mut self, item: T) {
+                self.enqueue_until(None, item).ok().expect("unreachable");
+            }
+
+            /// Adds an `item` to the end of the queue, blocking the current thread for at most
+            /// `timeout` until there's room for it
+            ///
+            /// Returns back the `item` if the queue is still full after `timeout`
+            pub fn enqueue_timeout(&mut self, item: T, timeout: Duration) -> Result<(), T> {
+                self.enqueue_until(Some(Instant::now() + timeout), item)
+            }
+
+            fn enqueue_until(&mut self, deadline: Option<Instant>, item: T) -> Result<(), T> {
+                let rb = self.rb;
+                let mut item = Some(item);
+
+                block(
+                    deadline,
+                    |waker| unsafe { rb.as_ref().0.producer_waker.register(waker) },
+                    || match self.enqueue(item.take().expect("unreachable")) {
+                        Ok(()) => Some(()),
+                        Err(i) => {
+                            item = Some(i);
+                            None
+                        }
+                    },
+                )
+                .ok_or_else(|| item.take().expect("unreachable"))
+            }
+        }
+
+        impl<'a, T, N, C> Consumer<'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Returns the item in the front of the queue, blocking the current thread until
+            /// there's one
+            pub fn dequeue_blocking(&mut self) -> T {
+                self.dequeue_until(None).expect("unreachable")
+            }
+
+            /// Returns the item in the front of the queue, blocking the current thread for at
+            /// most `timeout` until there's one
+            ///
+            /// Returns `None` if the queue is still empty after `timeout`
+            pub fn dequeue_timeout(&mut self, timeout: Duration) -> Option<T> {
+                self.dequeue_until(Some(Instant::now() + timeout))
+            }
+
+            fn dequeue_until(&mut self, deadline: Option<Instant>) -> Option<T> {
+                let rb = self.rb;
+
+                block(
+                    deadline,
+                    |waker| unsafe { rb.as_ref().0.consumer_waker.register(waker) },
+                    || self.dequeue(),
+                )
+            }
+        }
+    };
+}
+
+impl_!(u8, PowerOfTwo);
+impl_!(u16, PowerOfTwo);
+impl_!(usize);
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{consts::*, spsc::Queue};
+
+    #[test]
+    fn timeout() {
+        let mut rb: Queue<i32, U1> = Queue::new();
+        let (mut p, mut c) = rb.split();
+
+        assert_eq!(c.dequeue_timeout(Duration::from_millis(10)), None);
+        p.enqueue_blocking(0);
+        assert_eq!(p.enqueue_timeout(1, Duration::from_millis(10)), Err(1));
+        assert_eq!(c.dequeue_blocking(), 0);
+    }
+
+    #[test]
+    fn ping_pong() {
+        const N: i32 = 1024;
+
+        let mut rb: Queue<i32, U4> = Queue::new();
+        let (mut p, mut c) = rb.split();
+
+        scoped_threadpool::Pool::new(2).scoped(move |scope| {
+            scope.execute(move || {
+                for i in 0..N {
+                    p.enqueue_blocking(i);
+                }
+            });
+
+            scope.execute(move || {
+                for i in 0..N {
+                    assert_eq!(c.dequeue_blocking(), i);
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,226 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use generic_array::{typenum::PowerOfTwo, ArrayLength};
+
+use crate::{
+    sealed::spsc as sealed,
+    spsc::{Consumer, MultiCore, Producer},
+};
+
+/// Future returned by [`Producer::enqueue_async`](struct.Producer.html#method.enqueue_async)
+pub struct Enqueue<'p, 'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    producer: &'p mut Producer<'a, T, N, U, C>,
+    item: Option<T>,
+}
+
+// NOTE(Unpin) `item` is never pinned; it's moved into the queue
+impl<'p, 'a, T, N, U, C> Unpin for Enqueue<'p, 'a, T, N, U, C>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+}
+
+/// Future returned by [`Consumer::dequeue_async`](struct.Consumer.html#method.dequeue_async)
+pub struct Dequeue<'c, 'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    consumer: &'c mut Consumer<'a, T, N, U, C>,
+}
+
+// NOTE see `spsc::Queue`'s `impl_!` for why `$bound` is needed
+macro_rules! impl_ {
+    ($uxx:ident $(, $bound:ident)?) => {
+        impl<'a, T, N, C> Producer<'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Adds an `item` to the end of the queue, waiting for the consumer to make room for
+            /// it if the queue is full
+            ///
+            /// The task is woken up when the consumer dequeues an item.
+            pub fn enqueue_async(&mut self, item: T) -> Enqueue<'_, 'a, T, N, $uxx, C> {
+                Enqueue {
+                    producer: self,
+                    item: Some(item),
+                }
+            }
+        }
+
+        impl<'a, T, N, C> Consumer<'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Returns the item in the front of the queue, waiting for the producer to enqueue
+            /// one if the queue is empty
+            ///
+            /// The task is woken up when the producer enqueues an item.
+            pub fn dequeue_async(&mut self) -> Dequeue<'_, 'a, T, N, $uxx, C> {
+                Dequeue { consumer: self }
+            }
+        }
+
+        impl<'p, 'a, T, N, C> Future for Enqueue<'p, 'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                let item = this.item.take().expect("`Enqueue` polled after completion");
+
+                match this.producer.enqueue(item) {
+                    Ok(()) => Poll::Ready(()),
+                    Err(item) => {
+                        unsafe {
+                            this.producer
+                                .rb
+                                .as_ref()
+                                .0
+                                .producer_waker
+                                .register(cx.waker())
+                        };
+
+                        // the consumer may have dequeued an item before we registered the waker
+                        match this.producer.enqueue(item) {
+                            Ok(()) => Poll::Ready(()),
+                            Err(item) => {
+                                this.item = Some(item);
+                                Poll::Pending
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        impl<'c, 'a, T, N, C> Future for Dequeue<'c, 'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            type Output = T;
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+                let this = self.get_mut();
+
+                if let Some(item) = this.consumer.dequeue() {
+                    return Poll::Ready(item);
+                }
+
+                unsafe {
+                    this.consumer
+                        .rb
+                        .as_ref()
+                        .0
+                        .consumer_waker
+                        .register(cx.waker())
+                };
+
+                // the producer may have enqueued an item before we registered the waker
+                match this.consumer.dequeue() {
+                    Some(item) => Poll::Ready(item),
+                    None => Poll::Pending,
+                }
+            }
+        }
+    };
+}
+
+impl_!(u8, PowerOfTwo);
+impl_!(u16, PowerOfTwo);
+impl_!(usize);
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+        thread,
+    };
+
+    use crate::{
+        consts::*,
+        spsc::{thread_waker::thread_waker, Queue},
+    };
+
+    // a minimal executor that parks the current thread until the future is woken up
+    fn block_on<F>(mut future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let waker = thread_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // NOTE(unsafe) `future` is shadowed so it can't be moved after being pinned
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn ready() {
+        let mut rb: Queue<i32, U2> = Queue::new();
+        let (mut p, mut c) = rb.split();
+
+        block_on(p.enqueue_async(0));
+        block_on(p.enqueue_async(1));
+        assert_eq!(block_on(c.dequeue_async()), 0);
+        assert_eq!(block_on(c.dequeue_async()), 1);
+    }
+
+    #[test]
+    fn ping_pong() {
+        const N: i32 = 1024;
+
+        let mut rb: Queue<i32, U4> = Queue::new();
+        let (mut p, mut c) = rb.split();
+
+        run_concurrently(
+            move || {
+                for i in 0..N {
+                    block_on(p.enqueue_async(i));
+                }
+            },
+            move || {
+                for i in 0..N {
+                    assert_eq!(block_on(c.dequeue_async()), i);
+                }
+            },
+        );
+    }
+
+    // runs `a` and `b` on two threads that can borrow from the current stack frame
+    fn run_concurrently<A, B>(a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send,
+    {
+        scoped_threadpool::Pool::new(2).scoped(move |scope| {
+            scope.execute(a);
+            scope.execute(b);
+        });
+    }
+}
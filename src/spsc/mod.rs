@@ -62,6 +62,36 @@
 //! }
 //! ```
 //!
+//! - The [`spsc_split!`](../macro.spsc_split.html) macro creates a statically allocated queue and
+//! returns its `'static` end points at most once, which removes the need for the `unsafe` code and
+//! the risk of aliasing shown in the previous example.
+//!
+//! ```
+//! use heapless::{consts::*, spsc::{Consumer, Queue}, spsc_split};
+//!
+//! enum Event { A, B }
+//!
+//! fn init() -> Consumer<'static, Event, U4> {
+//!     let (mut producer, consumer) = spsc_split!(Queue<Event, U4>).unwrap();
+//!
+//!     // e.g. move `producer` into an interrupt handler's state
+//!     producer.enqueue(Event::A).ok().unwrap();
+//!
+//!     consumer
+//! }
+//! # init();
+//! ```
+//!
+//! - With the `async` Cargo feature enabled, the end points can also be used from `async` code.
+//! `Producer::enqueue_async` waits until there's room in the queue and `Consumer::dequeue_async`
+//! waits until there's an item in the queue. The waiting task is woken up by the other end point.
+//! This feature is not available on ARMv6-M and MSP430 as it requires compare-and-swap
+//! instructions.
+//!
+//! - With the `std` Cargo feature enabled, the end points can block the current thread, instead of
+//! busy waiting, until there's room / an item in the queue. See `Producer::enqueue_blocking`,
+//! `Producer::enqueue_timeout`, `Consumer::dequeue_blocking` and `Consumer::dequeue_timeout`.
+//!
 //! # Benchmarks
 //!
 //! Measured on a ARM Cortex-M3 core running at 8 MHz and with zero Flash wait cycles
@@ -80,15 +110,45 @@
 //! - The numbers reported correspond to the successful path (i.e. `Some` is returned by `dequeue`
 //! and `Ok` is returned by `enqueue`).
 
-use core::{cell::UnsafeCell, fmt, hash, marker::PhantomData, mem::MaybeUninit, ptr};
+use core::{cell::UnsafeCell, cmp, fmt, hash, marker::PhantomData, mem::MaybeUninit, ptr, slice};
 
-use generic_array::{ArrayLength, GenericArray};
+use generic_array::{typenum::PowerOfTwo, ArrayLength, GenericArray};
 use hash32;
 
-use crate::sealed::spsc as sealed;
-pub use split::{Consumer, Producer};
-
+#[cfg(feature = "async")]
+use crate::atomic_waker::AtomicWaker;
+use crate::{cache_padded::CachePadded, sealed::spsc as sealed};
+#[cfg(feature = "async")]
+pub use future::{Dequeue, Enqueue};
+pub use split::{Consumer, Drain, Producer, Reservation};
+
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "async")]
+mod future;
 mod split;
+// also used by the executor in `future`'s tests
+#[cfg(any(feature = "std", all(test, feature = "async")))]
+mod thread_waker;
+
+// Maps a free running head / tail index to the corresponding slot in the buffer
+//
+// NOTE when `N` is a power of two the slot is computed with a bit mask instead of a remainder; the
+// latter is a library call on cores that lack a hardware divider (e.g. Cortex-M0 and some RISC-V
+// cores). `POWER_OF_TWO` is an associated constant of the type level integer `N` so the condition
+// is evaluated at compile time; with optimizations enabled the untaken branch is removed, without
+// them it's still compiled in but never executed
+#[inline(always)]
+pub(crate) fn slot<N>(index: usize) -> usize
+where
+    N: sealed::Capacity,
+{
+    if N::POWER_OF_TWO {
+        index & N::MASK
+    } else {
+        index % N::USIZE
+    }
+}
 
 /// Multi core synchronization - a memory barrier is used for synchronization
 pub struct MultiCore;
@@ -121,15 +181,15 @@ where
         unsafe { &mut *self.v.get() }
     }
 
-    fn load_acquire(&self) -> U {
+    pub(crate) fn load_acquire(&self) -> U {
         unsafe { U::load_acquire::<C>(self.v.get()) }
     }
 
-    fn load_relaxed(&self) -> U {
+    pub(crate) fn load_relaxed(&self) -> U {
         U::load_relaxed(self.v.get())
     }
 
-    fn store_release(&self, val: U) {
+    pub(crate) fn store_release(&self, val: U) {
         unsafe { U::store_release::<C>(self.v.get(), val) }
     }
 }
@@ -148,12 +208,17 @@ where
 /// [`u16`]: struct.Queue.html#method.u16
 ///
 /// *IMPORTANT*: `spsc::Queue<_, _, u8>` has a maximum capacity of 255 elements; `spsc::Queue<_, _,
-/// u16>` has a maximum capacity of 65535 elements.
+/// u16>` has a maximum capacity of 65535 elements. These two index types also require `N` to be
+/// a power of 2 because the indices wrap around before `usize` does.
 ///
 /// `spsc::Queue` also comes in a single core variant. This variant can be created using the
 /// following constructors: `u8_sc`, `u16_sc`, `usize_sc` and `new_sc`. This variant is `unsafe` to
 /// create because the programmer must make sure that the queue's consumer and producer endpoints
 /// (if split) are kept on a single core for their entire lifetime.
+///
+/// When the producer and consumer endpoints run on different cores, enable the `cache-padded`
+/// Cargo feature to place the head and tail indices on separate cache lines. This avoids false
+/// sharing between the cores at the expense of a larger queue.
 pub struct Queue<T, N, U = usize, C = MultiCore>(
     #[doc(hidden)] pub crate::i::Queue<GenericArray<T, N>, U, C>,
 )
@@ -197,11 +262,133 @@ where
         }
     }
 
+    /// Returns a pair of slices which contain, in order, the contents of the queue
+    ///
+    /// The second slice is only non-empty when the contents wrap around the end of the underlying
+    /// buffer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::spsc::Queue;
+    /// use heapless::consts::*;
+    ///
+    /// let mut rb: Queue<u8, U4> = Queue::new();
+    /// rb.enqueue(0).unwrap();
+    /// rb.enqueue(1).unwrap();
+    /// rb.enqueue(2).unwrap();
+    /// rb.dequeue().unwrap();
+    /// rb.enqueue(3).unwrap();
+    /// rb.enqueue(4).unwrap();
+    ///
+    /// assert_eq!(rb.as_slices(), (&[1, 2, 3][..], &[4][..]));
+    /// ```
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (start, first, second) = self.ranges();
+        let p = self.0.buffer.as_ptr() as *const T;
+
+        unsafe {
+            (
+                slice::from_raw_parts(p.add(start), first),
+                slice::from_raw_parts(p, second),
+            )
+        }
+    }
+
+    /// Returns a pair of mutable slices which contain, in order, the contents of the queue
+    ///
+    /// The second slice is only non-empty when the contents wrap around the end of the underlying
+    /// buffer
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (start, first, second) = self.ranges();
+        let p = self.0.buffer.as_mut_ptr() as *mut T;
+
+        unsafe {
+            (
+                slice::from_raw_parts_mut(p.add(start), first),
+                slice::from_raw_parts_mut(p, second),
+            )
+        }
+    }
+
+    /// Rearranges the underlying buffer so that the contents of the queue are stored contiguously,
+    /// and returns them as a single mutable slice
+    ///
+    /// Computes in **O(N)** time if the contents wrap around the end of the underlying buffer;
+    /// otherwise this is a no-op
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heapless::spsc::Queue;
+    /// use heapless::consts::*;
+    ///
+    /// let mut rb: Queue<u8, U4> = Queue::new();
+    /// rb.enqueue(0).unwrap();
+    /// rb.enqueue(1).unwrap();
+    /// rb.dequeue().unwrap();
+    /// rb.enqueue(2).unwrap();
+    /// rb.enqueue(3).unwrap();
+    /// rb.enqueue(4).unwrap();
+    ///
+    /// assert_eq!(rb.make_contiguous(), [1, 2, 3, 4]);
+    /// assert_eq!(rb.dequeue(), Some(1));
+    /// ```
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        let (start, _, second) = self.ranges();
+
+        if second != 0 {
+            let len = self.len_usize();
+
+            // NOTE(MaybeUninit) the buffer contains uninitialized slots; these are moved around
+            // but never read as `T`
+            unsafe {
+                slice::from_raw_parts_mut(
+                    self.0.buffer.as_mut_ptr() as *mut MaybeUninit<T>,
+                    N::to_usize(),
+                )
+                .rotate_left(start);
+            }
+
+            *self.0.head.get_mut() = U::truncate(0);
+            *self.0.tail.get_mut() = U::truncate(len);
+        }
+
+        self.as_mut_slices().0
+    }
+
+    // wakes up the task that's waiting for free space in the queue; a no-op unless the `async`
+    // feature is enabled
+    #[inline(always)]
+    fn wake_producer(&self) {
+        #[cfg(feature = "async")]
+        self.0.producer_waker.wake();
+    }
+
+    // wakes up the task that's waiting for items in the queue; a no-op unless the `async` feature
+    // is enabled
+    #[inline(always)]
+    fn wake_consumer(&self) {
+        #[cfg(feature = "async")]
+        self.0.consumer_waker.wake();
+    }
+
+    // returns the start of the contents in the buffer, the number of items in the first half of
+    // the ring and the number of items in the second half
+    fn ranges(&self) -> (usize, usize, usize) {
+        let len = self.len_usize();
+        let start = slot::<N>(self.0.head.load_relaxed().into());
+        let first = cmp::min(len, N::to_usize() - start);
+
+        (start, first, len - first)
+    }
+
     fn len_usize(&self) -> usize {
         let head = self.0.head.load_relaxed().into();
         let tail = self.0.tail.load_relaxed().into();
 
-        tail.wrapping_sub(head)
+        // NOTE the indices wrap around at `U::MAX`, not at `usize::MAX`
+        tail.wrapping_sub(head) & U::truncate(usize::max_value()).into()
     }
 }
 
@@ -290,11 +477,13 @@ where
     }
 }
 
+// NOTE `$bound` is `PowerOfTwo` for the indices that wrap around before `usize` does; with any
+// other `N` the slot of the index is discontinuous at the wrap around point
 macro_rules! impl_ {
-    ($uxx:ident, $uxx_sc:ident) => {
+    ($uxx:ident, $uxx_sc:ident $(, $bound:ident)?) => {
         impl<T, N> Queue<T, N, $uxx, MultiCore>
         where
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
         {
             /// Creates an empty queue with a fixed capacity of `N`
             pub fn $uxx() -> Self {
@@ -308,15 +497,19 @@ macro_rules! impl_ {
             pub const fn $uxx() -> Self {
                 crate::i::Queue {
                     buffer: MaybeUninit::uninit(),
-                    head: Atomic::new(0),
-                    tail: Atomic::new(0),
+                    head: CachePadded::new(Atomic::new(0)),
+                    tail: CachePadded::new(Atomic::new(0)),
+                    #[cfg(feature = "async")]
+                    producer_waker: AtomicWaker::new(),
+                    #[cfg(feature = "async")]
+                    consumer_waker: AtomicWaker::new(),
                 }
             }
         }
 
         impl<T, N> Queue<T, N, $uxx, SingleCore>
         where
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
         {
             /// Creates an empty queue with a fixed capacity of `N` (single core variant)
             pub unsafe fn $uxx_sc() -> Self {
@@ -330,28 +523,30 @@ macro_rules! impl_ {
             pub const unsafe fn $uxx_sc() -> Self {
                 crate::i::Queue {
                     buffer: MaybeUninit::uninit(),
-                    head: Atomic::new(0),
-                    tail: Atomic::new(0),
+                    head: CachePadded::new(Atomic::new(0)),
+                    tail: CachePadded::new(Atomic::new(0)),
+                    #[cfg(feature = "async")]
+                    producer_waker: AtomicWaker::new(),
+                    #[cfg(feature = "async")]
+                    consumer_waker: AtomicWaker::new(),
                 }
             }
         }
 
         impl<T, N, C> Queue<T, N, $uxx, C>
         where
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
             C: sealed::XCore,
         {
             /// Returns the item in the front of the queue, or `None` if the queue is empty
             pub fn dequeue(&mut self) -> Option<T> {
-                let cap = self.capacity();
-
                 let head = self.0.head.get_mut();
                 let tail = self.0.tail.get_mut();
 
                 let p = self.0.buffer.as_ptr();
 
                 if *head != *tail {
-                    let item = unsafe { (p as *const T).add(usize::from(*head % cap)).read() };
+                    let item = unsafe { (p as *const T).add(slot::<N>((*head).into())).read() };
                     *head = head.wrapping_add(1);
                     Some(item)
                 } else {
@@ -359,6 +554,36 @@ macro_rules! impl_ {
                 }
             }
 
+            /// Returns a reference to the item in the front of the queue without dequeuing it, or
+            /// `None` if the queue is empty
+            pub fn peek(&self) -> Option<&T> {
+                let head = self.0.head.load_relaxed();
+                let tail = self.0.tail.load_relaxed();
+
+                let p = self.0.buffer.as_ptr();
+
+                if head != tail {
+                    Some(unsafe { &*(p as *const T).add(slot::<N>(head.into())) })
+                } else {
+                    None
+                }
+            }
+
+            /// Returns a mutable reference to the item in the front of the queue without dequeuing
+            /// it, or `None` if the queue is empty
+            pub fn peek_mut(&mut self) -> Option<&mut T> {
+                let head = *self.0.head.get_mut();
+                let tail = *self.0.tail.get_mut();
+
+                let p = self.0.buffer.as_mut_ptr();
+
+                if head != tail {
+                    Some(unsafe { &mut *(p as *mut T).add(slot::<N>(head.into())) })
+                } else {
+                    None
+                }
+            }
+
             /// Adds an `item` to the end of the queue
             ///
             /// Returns back the `item` if the queue is full
@@ -384,14 +609,13 @@ macro_rules! impl_ {
             /// to create a copy of `item`, which could result in `T`'s destructor running on `item`
             /// twice.
             pub unsafe fn enqueue_unchecked(&mut self, item: T) {
-                let cap = self.capacity();
                 let tail = self.0.tail.get_mut();
 
                 // NOTE(ptr::write) the memory slot that we are about to write to is
                 // uninitialized. We use `ptr::write` to avoid running `T`'s destructor on the
                 // uninitialized memory
                 (self.0.buffer.as_mut_ptr() as *mut T)
-                    .add(usize::from(*tail % cap))
+                    .add(slot::<N>((*tail).into()))
                     .write(item);
                 *tail = tail.wrapping_add(1);
             }
@@ -412,14 +636,18 @@ macro_rules! impl_ {
         impl<T, N, C> Clone for Queue<T, N, $uxx, C>
         where
             T: Clone,
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
             C: sealed::XCore,
         {
             fn clone(&self) -> Self {
                 let mut new: Queue<T, N, $uxx, C> = Queue(crate::i::Queue {
                     buffer: MaybeUninit::uninit(),
-                    head: Atomic::new(0),
-                    tail: Atomic::new(0),
+                    head: CachePadded::new(Atomic::new(0)),
+                    tail: CachePadded::new(Atomic::new(0)),
+                    #[cfg(feature = "async")]
+                    producer_waker: AtomicWaker::new(),
+                    #[cfg(feature = "async")]
+                    consumer_waker: AtomicWaker::new(),
                 });
 
                 for s in self.iter() {
@@ -471,8 +699,8 @@ where
     }
 }
 
-impl_!(u8, u8_sc);
-impl_!(u16, u16_sc);
+impl_!(u8, u8_sc, PowerOfTwo);
+impl_!(u16, u16_sc, PowerOfTwo);
 impl_!(usize, usize_sc);
 
 impl<T, N, U, C, N2, U2, C2> PartialEq<Queue<T, N2, U2, C2>> for Queue<T, N, U, C>
@@ -551,11 +779,10 @@ macro_rules! iterator {
 
             fn next(&mut self) -> Option<$elem> {
                 if self.index < self.len {
-                    let head = self.rb.0.head.load_relaxed().into();
+                    let head: usize = self.rb.0.head.load_relaxed().into();
 
-                    let cap = self.rb.capacity().into();
                     let ptr = self.rb.0.buffer.$asptr() as $ptr;
-                    let i = (head + self.index) % cap;
+                    let i = slot::<N>(head.wrapping_add(self.index));
                     self.index += 1;
                     Some(unsafe { $mkref!(*ptr.offset(i as isize)) })
                 } else {
@@ -689,6 +916,87 @@ mod tests {
         assert_eq!(rb.dequeue(), None);
     }
 
+    #[test]
+    fn peek() {
+        let mut rb: Queue<i32, U2> = Queue::new();
+
+        assert_eq!(rb.peek(), None);
+
+        rb.enqueue(0).unwrap();
+        rb.enqueue(1).unwrap();
+
+        assert_eq!(rb.peek(), Some(&0));
+        *rb.peek_mut().unwrap() = 2;
+        assert_eq!(rb.dequeue(), Some(2));
+        assert_eq!(rb.peek(), Some(&1));
+    }
+
+    #[test]
+    fn as_slices() {
+        let mut rb: Queue<i32, U4> = Queue::new();
+
+        assert_eq!(rb.as_slices(), (&[][..], &[][..]));
+
+        rb.enqueue(0).unwrap();
+        rb.enqueue(1).unwrap();
+        rb.enqueue(2).unwrap();
+        assert_eq!(rb.as_slices(), (&[0, 1, 2][..], &[][..]));
+
+        rb.dequeue().unwrap();
+        rb.dequeue().unwrap();
+        rb.enqueue(3).unwrap();
+        rb.enqueue(4).unwrap();
+        rb.enqueue(5).unwrap();
+
+        {
+            let (a, b) = rb.as_mut_slices();
+            a[0] = 12;
+            b[0] = 14;
+        }
+        assert_eq!(rb.as_slices(), (&[12, 3][..], &[14, 5][..]));
+    }
+
+    #[test]
+    fn make_contiguous() {
+        let mut rb: Queue<i32, U4, u8> = Queue::u8();
+
+        rb.enqueue(0).unwrap();
+        rb.enqueue(1).unwrap();
+        rb.enqueue(2).unwrap();
+        rb.dequeue().unwrap();
+        rb.dequeue().unwrap();
+        rb.enqueue(3).unwrap();
+
+        // already contiguous
+        assert_eq!(rb.make_contiguous(), [2, 3]);
+
+        rb.enqueue(4).unwrap();
+        assert_eq!(rb.make_contiguous(), [2, 3, 4]);
+        assert_eq!(rb.as_slices(), (&[2, 3, 4][..], &[][..]));
+
+        rb.enqueue(5).unwrap();
+        assert!(rb.enqueue(6).is_err());
+        assert_eq!(rb.iter().cloned().collect::<std::vec::Vec<_>>(), [2, 3, 4, 5]);
+        assert_eq!(rb.dequeue(), Some(2));
+    }
+
+    #[test]
+    fn u8_index_wrap_around() {
+        // the capacity is limited to 255 by the `u8` indices, but the buffer has 256 slots
+        let mut rb: Queue<u16, U256, u8> = Queue::u8();
+
+        for i in 0..255 {
+            rb.enqueue(i).unwrap();
+        }
+
+        for i in 255..1024 {
+            assert_eq!(rb.dequeue(), Some(i - 255));
+            rb.enqueue(i).unwrap();
+        }
+
+        assert_eq!(rb.iter().next(), Some(&(1024 - 255)));
+    }
+
     #[test]
     #[cfg(feature = "smaller-atomics")]
     fn u8() {
@@ -701,6 +1009,16 @@ mod tests {
         assert!(rb.enqueue(0).is_err());
     }
 
+    #[test]
+    fn slot() {
+        use super::slot;
+
+        assert_eq!(slot::<U4>(5), 1);
+        assert_eq!(slot::<U4>(usize::max_value()), 3);
+        assert_eq!(slot::<U3>(5), 2);
+        assert_eq!(slot::<U3>(usize::max_value()), usize::max_value() % 3);
+    }
+
     #[test]
     fn wrap_around() {
         let mut rb: Queue<i32, U3> = Queue::new();
@@ -1,12 +1,57 @@
-use core::{marker::PhantomData, ptr::NonNull};
+use core::{cmp, marker::PhantomData, mem::MaybeUninit, ptr, ptr::NonNull, slice};
 
-use generic_array::ArrayLength;
+use generic_array::{typenum::PowerOfTwo, ArrayLength};
 
 use crate::{
     sealed::spsc as sealed,
-    spsc::{MultiCore, Queue},
+    spsc::{slot, MultiCore, Queue},
 };
 
+/// Creates a statically allocated queue and splits it into `'static` producer and consumer end
+/// points
+///
+/// Returns `None` if this macro invocation has already returned the end points; i.e. each
+/// invocation hands out its end points at most once, so they can't be aliased.
+///
+/// The queue type must be given. A `const` expression that creates the queue can be given as
+/// well; by default the queue is created using `Queue::new`.
+///
+/// # Examples
+///
+/// ```
+/// use heapless::{consts::*, spsc::Queue, spsc_split};
+///
+/// fn init() {
+///     let (mut producer, mut consumer) = spsc_split!(Queue<u8, U4>).unwrap();
+///
+///     // the end points can be moved into other execution contexts, like interrupt handlers or
+///     // threads
+///     std::thread::spawn(move || producer.enqueue(42).unwrap()).join().unwrap();
+///
+///     assert_eq!(consumer.dequeue(), Some(42));
+/// }
+///
+/// init();
+///
+/// // a queue that uses `u8` indices
+/// let endpoints = spsc_split!(Queue<u8, U4, u8> = Queue(heapless::i::Queue::u8()));
+/// assert!(endpoints.is_some());
+/// ```
+///
+/// *NOTE:* On architectures that lack compare-and-swap instructions, ARMv6-M and MSP430, the end
+/// points are claimed inside an interrupt-free critical section.
+#[macro_export]
+macro_rules! spsc_split {
+    ($ty:ty) => {
+        $crate::spsc_split!($ty = $crate::spsc::Queue($crate::i::Queue::new()))
+    };
+    ($ty:ty = $expr:expr) => {{
+        static QUEUE: $crate::i::Singleton<$ty> = $crate::i::Singleton::new($expr);
+
+        QUEUE.take().map(|queue| queue.split())
+    }};
+}
+
 impl<T, N, U, C> Queue<T, N, U, C>
 where
     N: ArrayLength<T>,
@@ -36,7 +81,7 @@ where
     U: sealed::Uxx,
     C: sealed::XCore,
 {
-    rb: NonNull<Queue<T, N, U, C>>,
+    pub(super) rb: NonNull<Queue<T, N, U, C>>,
     _marker: PhantomData<&'a ()>,
 }
 
@@ -57,7 +102,7 @@ where
     U: sealed::Uxx,
     C: sealed::XCore,
 {
-    rb: NonNull<Queue<T, N, U, C>>,
+    pub(super) rb: NonNull<Queue<T, N, U, C>>,
     _marker: PhantomData<&'a ()>,
 }
 
@@ -69,11 +114,56 @@ where
 {
 }
 
+/// A reserved slot at the end of a queue
+///
+/// This `struct` is created by the [`reserve`](struct.Producer.html#method.reserve) method. The
+/// item constructed in the slot is added to the queue when the reservation is committed; dropping
+/// the reservation without committing it leaves the queue unchanged.
+pub struct Reservation<'p, 'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    producer: &'p mut Producer<'a, T, N, U, C>,
+}
+
+/// A draining iterator over the items of a queue
+///
+/// This `struct` is created by the [`drain`](struct.Consumer.html#method.drain) method
+pub struct Drain<'a, T, N, U = usize, C = MultiCore>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    rb: NonNull<Queue<T, N, U, C>>,
+    head: U,
+    tail: U,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a, T, N, U, C> Drop for Drain<'a, T, N, U, C>
+where
+    N: ArrayLength<T>,
+    U: sealed::Uxx,
+    C: sealed::XCore,
+{
+    fn drop(&mut self) {
+        // NOTE publish the new head, and wake the producer, once for the whole batch
+        unsafe {
+            self.rb.as_ref().0.head.store_release(self.head); // ▲
+            self.rb.as_ref().wake_producer();
+        }
+    }
+}
+
+// NOTE see `spsc::Queue`'s `impl_!` for why `$bound` is needed
 macro_rules! impl_ {
-    ($uxx:ident) => {
+    ($uxx:ident $(, $bound:ident)?) => {
         impl<'a, T, N, C> Consumer<'a, T, N, $uxx, C>
         where
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
             C: sealed::XCore,
         {
             /// Returns if there are any items to dequeue. When this returns true, at least the
@@ -96,6 +186,174 @@ macro_rules! impl_ {
                 }
             }
 
+            /// Returns a reference to the item in the front of the queue without dequeuing it, or
+            /// `None` if the queue is empty
+            ///
+            /// The producer won't overwrite the item while it's borrowed
+            pub fn peek(&self) -> Option<&T> {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                if head != tail {
+                    Some(unsafe { &*self._slot(head) })
+                } else {
+                    None
+                }
+            }
+
+            /// Returns a mutable reference to the item in the front of the queue without dequeuing
+            /// it, or `None` if the queue is empty
+            ///
+            /// The producer won't overwrite the item while it's borrowed
+            pub fn peek_mut(&mut self) -> Option<&mut T> {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                if head != tail {
+                    Some(unsafe { &mut *self._slot(head) })
+                } else {
+                    None
+                }
+            }
+
+            /// Moves items from the front of the queue into `buf`
+            ///
+            /// Returns the number of items that were moved, which is the smaller of `buf.len()`
+            /// and the number of items in the queue. The head of the queue is advanced with a
+            /// single store.
+            pub fn dequeue_into(&mut self, buf: &mut [T]) -> usize
+            where
+                T: Copy,
+            {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                let n = cmp::min(buf.len(), usize::from(tail.wrapping_sub(head)));
+                let start = slot::<N>(head.into());
+                let first = cmp::min(n, N::to_usize() - start);
+
+                unsafe {
+                    let p = self.rb.as_ref().0.buffer.as_ptr() as *const T;
+                    ptr::copy_nonoverlapping(p.add(start), buf.as_mut_ptr(), first);
+                    ptr::copy_nonoverlapping(p, buf.as_mut_ptr().add(first), n - first);
+
+                    // NOTE(as) `n` is not greater than the capacity of the queue, which fits in
+                    // `$uxx`
+                    self.rb
+                        .as_ref()
+                        .0
+                        .head
+                        .store_release(head.wrapping_add(n as $uxx)); // ▲
+                    self.rb.as_ref().wake_producer();
+                }
+
+                n
+            }
+
+            /// Returns an iterator that removes the items that are currently in the queue
+            ///
+            /// The iterator only yields the items that were in the queue when it was created. The
+            /// head of the queue is advanced once, when the iterator is dropped; items that are not
+            /// yielded, because the iterator was dropped before it was exhausted, remain in the
+            /// queue.
+            ///
+            /// Forgetting the iterator (e.g. with `mem::forget`) leaves *all* the items in the
+            /// queue, including the ones that have been yielded. This is why the items must be
+            /// `Copy`.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use heapless::spsc::Queue;
+            /// use heapless::consts::*;
+            ///
+            /// let mut rb: Queue<u8, U8> = Queue::new();
+            /// let (mut p, mut c) = rb.split();
+            ///
+            /// p.enqueue_slice(&[1, 2, 3]);
+            ///
+            /// let sum: u8 = c.drain().sum();
+            /// assert_eq!(sum, 6);
+            /// assert_eq!(c.dequeue(), None);
+            /// ```
+            pub fn drain(&mut self) -> Drain<'_, T, N, $uxx, C>
+            where
+                T: Copy,
+            {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                Drain {
+                    rb: self.rb,
+                    head,
+                    tail,
+                    _marker: PhantomData,
+                }
+            }
+
+            /// Returns the largest contiguous slice of items, starting from the front of the queue,
+            /// that can be read in place
+            ///
+            /// The slice may be shorter than the number of items in the queue when the items wrap
+            /// around the end of the underlying buffer. The items remain in the queue until they
+            /// are [`release`](struct.Consumer.html#method.release)d.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use heapless::spsc::Queue;
+            /// use heapless::consts::*;
+            ///
+            /// let mut rb: Queue<u8, U8> = Queue::new();
+            /// let (mut p, mut c) = rb.split();
+            ///
+            /// for &byte in b"hello" {
+            ///     p.enqueue(byte).unwrap();
+            /// }
+            ///
+            /// // e.g. hand this region to a DMA transfer
+            /// assert_eq!(c.read_grant(), b"hello");
+            ///
+            /// // .. once the transfer is complete
+            /// c.release(5);
+            /// assert!(c.read_grant().is_empty());
+            /// ```
+            pub fn read_grant(&self) -> &[T] {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                let start = slot::<N>(head.into());
+                let len = cmp::min(usize::from(tail.wrapping_sub(head)), N::to_usize() - start);
+
+                unsafe { slice::from_raw_parts(self._slot(head), len) }
+            }
+
+            /// Removes `n` items from the front of the queue, dropping them
+            ///
+            /// This is meant to be used after reading the items through
+            /// [`read_grant`](struct.Consumer.html#method.read_grant). The head of the queue is
+            /// advanced with a single store.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `n` is greater than the number of items in the queue
+            pub fn release(&mut self, n: usize) {
+                let head = unsafe { self.rb.as_ref().0.head.load_relaxed() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_acquire() }; // ▼
+
+                assert!(n <= usize::from(tail.wrapping_sub(head)));
+
+                let mut index = head;
+                for _ in 0..n {
+                    unsafe { ptr::drop_in_place(self._slot(index)) }
+                    index = index.wrapping_add(1);
+                }
+                unsafe {
+                    self.rb.as_ref().0.head.store_release(index); // ▲
+                    self.rb.as_ref().wake_producer();
+                }
+            }
+
             /// Returns the item in the front of the queue, without checking if it's empty
             ///
             /// # Unsafety
@@ -108,21 +366,21 @@ macro_rules! impl_ {
             }
 
             unsafe fn _dequeue(&mut self, head: $uxx) -> T {
-                let rb = self.rb.as_ref();
-
-                let cap = rb.capacity();
-
-                let item = (rb.0.buffer.as_ptr() as *const T)
-                    .add(usize::from(head % cap))
-                    .read();
-                rb.0.head.store_release(head.wrapping_add(1)); // ▲
+                let item = self._slot(head).read();
+                self.rb.as_ref().0.head.store_release(head.wrapping_add(1)); // ▲
+                self.rb.as_ref().wake_producer();
                 item
             }
+
+            // NOTE the slot is owned by the consumer until `head` is advanced past it
+            unsafe fn _slot(&self, head: $uxx) -> *mut T {
+                (self.rb.as_ref().0.buffer.as_ptr() as *mut T).add(slot::<N>(head.into()))
+            }
         }
 
         impl<'a, T, N, C> Producer<'a, T, N, $uxx, C>
         where
-            N: ArrayLength<T>,
+            N: ArrayLength<T> $(+ $bound)?,
             C: sealed::XCore,
         {
             /// Returns if there is any space to enqueue a new item. When this returns true, at
@@ -161,6 +419,166 @@ macro_rules! impl_ {
                 }
             }
 
+            /// Copies as many items from `items` as fit into the end of the queue
+            ///
+            /// Returns the number of items that were added to the queue. The tail of the queue is
+            /// advanced with a single store.
+            pub fn enqueue_slice(&mut self, items: &[T]) -> usize
+            where
+                T: Copy,
+            {
+                let cap = unsafe { self.rb.as_ref().capacity() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_relaxed() };
+                let head = unsafe { self.rb.as_ref().0.head.load_acquire() }; // ▼
+
+                let n = cmp::min(items.len(), usize::from(cap - tail.wrapping_sub(head)));
+                let start = slot::<N>(tail.into());
+                let first = cmp::min(n, N::to_usize() - start);
+
+                unsafe {
+                    let p = self.rb.as_mut().0.buffer.as_mut_ptr() as *mut T;
+                    ptr::copy_nonoverlapping(items.as_ptr(), p.add(start), first);
+                    ptr::copy_nonoverlapping(items.as_ptr().add(first), p, n - first);
+
+                    // NOTE(as) `n` is not greater than the capacity of the queue, which fits in
+                    // `$uxx`
+                    self.rb
+                        .as_ref()
+                        .0
+                        .tail
+                        .store_release(tail.wrapping_add(n as $uxx)); // ▲
+                    self.rb.as_ref().wake_consumer();
+                }
+
+                n
+            }
+
+            /// Returns the largest contiguous region of free slots, starting from the end of the
+            /// queue, that can be written in place
+            ///
+            /// The region may be shorter than the free space in the queue when the free slots wrap
+            /// around the end of the underlying buffer. The written items are added to the queue
+            /// when they are [`commit`](struct.Producer.html#method.commit)ted.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use core::mem::MaybeUninit;
+            ///
+            /// use heapless::spsc::Queue;
+            /// use heapless::consts::*;
+            ///
+            /// let mut rb: Queue<u8, U8> = Queue::new();
+            /// let (mut p, mut c) = rb.split();
+            ///
+            /// // e.g. hand this region to a DMA transfer
+            /// let grant = p.write_grant();
+            /// assert_eq!(grant.len(), 8);
+            /// for (slot, &byte) in grant.iter_mut().zip(b"hello") {
+            ///     *slot = MaybeUninit::new(byte);
+            /// }
+            ///
+            /// // .. once the transfer is complete
+            /// unsafe { p.commit(5) }
+            /// assert_eq!(c.read_grant(), b"hello");
+            /// ```
+            pub fn write_grant(&mut self) -> &mut [MaybeUninit<T>] {
+                let cap = unsafe { self.rb.as_ref().capacity() };
+                let tail = unsafe { self.rb.as_ref().0.tail.load_relaxed() };
+                let head = unsafe { self.rb.as_ref().0.head.load_acquire() }; // ▼
+
+                let free = usize::from(cap - tail.wrapping_sub(head));
+                let start = slot::<N>(tail.into());
+                let len = cmp::min(free, N::to_usize() - start);
+
+                unsafe {
+                    slice::from_raw_parts_mut(
+                        (self.rb.as_mut().0.buffer.as_mut_ptr() as *mut MaybeUninit<T>).add(start),
+                        len,
+                    )
+                }
+            }
+
+            /// Adds the first `n` items of the region returned by
+            /// [`write_grant`](struct.Producer.html#method.write_grant) to the end of the queue
+            ///
+            /// The tail of the queue is advanced with a single store.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `n` is greater than the length of the region returned by
+            /// `write_grant`
+            ///
+            /// # Unsafety
+            ///
+            /// The first `n` slots of the region must have been initialized
+            pub unsafe fn commit(&mut self, n: usize) {
+                assert!(n <= self.write_grant().len());
+
+                let tail = self.rb.as_ref().0.tail.load_relaxed();
+                // NOTE(as) `n` is not greater than the capacity of the queue, which fits in `$uxx`
+                self.rb
+                    .as_ref()
+                    .0
+                    .tail
+                    .store_release(tail.wrapping_add(n as $uxx)); // ▲
+                self.rb.as_ref().wake_consumer();
+            }
+
+            /// Reserves the slot at the end of the queue so that an item can be constructed in
+            /// place
+            ///
+            /// Returns `None` if the queue is full
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// use heapless::spsc::Queue;
+            /// use heapless::consts::*;
+            ///
+            /// let mut rb: Queue<[u8; 1024], U2> = Queue::new();
+            /// let (mut p, mut c) = rb.split();
+            ///
+            /// let mut reservation = p.reserve().unwrap();
+            /// // construct the frame directly in the queue's buffer
+            /// unsafe {
+            ///     let frame = reservation.slot().as_mut_ptr();
+            ///     frame.write_bytes(0, 1);
+            ///     (*frame)[0] = 1;
+            ///     reservation.commit();
+            /// }
+            ///
+            /// assert_eq!(c.peek().map(|frame| frame[0]), Some(1));
+            /// ```
+            pub fn reserve(&mut self) -> Option<Reservation<'_, 'a, T, N, $uxx, C>> {
+                if self.ready() {
+                    Some(Reservation { producer: self })
+                } else {
+                    None
+                }
+            }
+
+            /// Constructs an item in place at the end of the queue
+            ///
+            /// Returns back `f` if the queue is full
+            ///
+            /// # Unsafety
+            ///
+            /// `f` must initialize the slot it's given
+            pub unsafe fn enqueue_with<F>(&mut self, f: F) -> Result<(), F>
+            where
+                F: FnOnce(&mut MaybeUninit<T>),
+            {
+                match self.reserve() {
+                    Some(mut reservation) => {
+                        f(reservation.slot());
+                        reservation.commit();
+                        Ok(())
+                    }
+                    None => Err(f),
+                }
+            }
+
             /// Adds an `item` to the end of the queue without checking if it's full
             ///
             /// # Unsafety
@@ -178,27 +596,80 @@ macro_rules! impl_ {
             unsafe fn _enqueue(&mut self, tail: $uxx, item: T) {
                 let rb = self.rb.as_mut();
 
-                let cap = rb.capacity();
-
                 // NOTE(ptr::write) the memory slot that we are about to write to is
                 // uninitialized. We use `ptr::write` to avoid running `T`'s destructor on the
                 // uninitialized memory
                 (rb.0.buffer.as_mut_ptr() as *mut T)
-                    .add(usize::from(tail % cap))
+                    .add(slot::<N>(tail.into()))
                     .write(item);
                 rb.0.tail.store_release(tail.wrapping_add(1)); // ▲
+                rb.wake_consumer();
+            }
+        }
+
+        impl<'p, 'a, T, N, C> Reservation<'p, 'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            /// Returns the slot where the item must be constructed
+            pub fn slot(&mut self) -> &mut MaybeUninit<T> {
+                &mut self.producer.write_grant()[0]
+            }
+
+            /// Adds the item in the slot to the end of the queue
+            ///
+            /// # Unsafety
+            ///
+            /// The slot must have been initialized
+            pub unsafe fn commit(self) {
+                self.producer.commit(1)
+            }
+        }
+
+        impl<'a, T, N, C> Iterator for Drain<'a, T, N, $uxx, C>
+        where
+            N: ArrayLength<T> $(+ $bound)?,
+            C: sealed::XCore,
+        {
+            type Item = T;
+
+            fn next(&mut self) -> Option<T> {
+                if self.head != self.tail {
+                    let item = unsafe {
+                        (self.rb.as_ref().0.buffer.as_ptr() as *const T)
+                            .add(slot::<N>(self.head.into()))
+                            .read()
+                    };
+                    self.head = self.head.wrapping_add(1);
+
+                    Some(item)
+                } else {
+                    None
+                }
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                let len = usize::from(self.tail.wrapping_sub(self.head));
+                (len, Some(len))
             }
         }
     };
 }
 
-impl_!(u8);
-impl_!(u16);
+impl_!(u8, PowerOfTwo);
+impl_!(u16, PowerOfTwo);
 impl_!(usize);
 
 #[cfg(test)]
 mod tests {
-    use crate::{consts::*, spsc::Queue};
+    use core::mem::{self, MaybeUninit};
+    use std::rc::Rc;
+
+    use crate::{
+        consts::*,
+        spsc::{Consumer, Producer, Queue},
+    };
 
     #[test]
     fn sanity() {
@@ -212,4 +683,173 @@ mod tests {
 
         assert_eq!(c.dequeue(), Some(0));
     }
+
+    #[test]
+    fn peek() {
+        let mut rb: Queue<i32, U2> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        assert_eq!(c.peek(), None);
+
+        p.enqueue(0).unwrap();
+        p.enqueue(1).unwrap();
+
+        assert_eq!(c.peek(), Some(&0));
+        *c.peek_mut().unwrap() += 10;
+        assert_eq!(c.dequeue(), Some(10));
+        assert_eq!(c.peek(), Some(&1));
+        assert_eq!(c.dequeue(), Some(1));
+        assert_eq!(c.peek_mut(), None);
+    }
+
+    #[test]
+    fn grants() {
+        let mut rb: Queue<u8, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        p.enqueue(0).unwrap();
+        p.enqueue(1).unwrap();
+        p.enqueue(2).unwrap();
+        c.release(2);
+
+        // the free slots wrap around the end of the buffer
+        assert_eq!(p.write_grant().len(), 1);
+        p.write_grant()[0] = MaybeUninit::new(3);
+        unsafe { p.commit(1) }
+
+        assert_eq!(p.write_grant().len(), 2);
+        for slot in p.write_grant() {
+            *slot = MaybeUninit::new(4);
+        }
+        unsafe { p.commit(2) }
+        assert!(p.write_grant().is_empty());
+
+        // the items wrap around the end of the buffer
+        assert_eq!(c.read_grant(), [2, 3]);
+        c.release(2);
+        assert_eq!(c.read_grant(), [4, 4]);
+        c.release(2);
+        assert!(c.read_grant().is_empty());
+    }
+
+    #[test]
+    fn release_drops() {
+        let rc = Rc::new(());
+        let mut rb: Queue<Rc<()>, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        p.enqueue(rc.clone()).unwrap();
+        p.enqueue(rc.clone()).unwrap();
+        c.release(2);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn slices() {
+        let mut rb: Queue<u8, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        assert_eq!(p.enqueue_slice(&[0, 1, 2]), 3);
+        let mut buf = [0; 2];
+        assert_eq!(c.dequeue_into(&mut buf), 2);
+        assert_eq!(buf, [0, 1]);
+
+        // wraps around the end of the buffer
+        assert_eq!(p.enqueue_slice(&[3, 4, 5, 6]), 3);
+        let mut buf = [0; 8];
+        assert_eq!(c.dequeue_into(&mut buf), 4);
+        assert_eq!(buf[..4], [2, 3, 4, 5]);
+        assert_eq!(c.dequeue_into(&mut buf), 0);
+    }
+
+    #[test]
+    fn drain() {
+        let mut rb: Queue<i32, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        p.enqueue(0).unwrap();
+        p.enqueue(1).unwrap();
+        p.enqueue(2).unwrap();
+
+        {
+            let mut drain = c.drain();
+            assert_eq!(drain.size_hint(), (3, Some(3)));
+            assert_eq!(drain.next(), Some(0));
+            assert_eq!(drain.next(), Some(1));
+        }
+
+        assert_eq!(c.dequeue(), Some(2));
+        assert_eq!(c.drain().next(), None);
+    }
+
+    #[test]
+    fn drain_forget() {
+        let mut rb: Queue<i32, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        p.enqueue(0).unwrap();
+        p.enqueue(1).unwrap();
+
+        let mut drain = c.drain();
+        assert_eq!(drain.next(), Some(0));
+        mem::forget(drain);
+
+        // the head was never advanced so all the items are still in the queue
+        assert_eq!(c.dequeue(), Some(0));
+        assert_eq!(c.dequeue(), Some(1));
+        assert_eq!(c.dequeue(), None);
+    }
+
+    #[test]
+    fn split_static() {
+        fn endpoints() -> Option<(Producer<'static, i32, U4>, Consumer<'static, i32, U4>)> {
+            crate::spsc_split!(Queue<i32, U4>)
+        }
+
+        let (mut p, mut c) = endpoints().unwrap();
+        assert!(endpoints().is_none());
+
+        p.enqueue(0).unwrap();
+        assert_eq!(c.dequeue(), Some(0));
+    }
+
+    #[test]
+    fn in_place() {
+        let mut rb: Queue<Rc<i32>, U2> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        // uncommitted reservations don't change the queue
+        p.reserve().unwrap();
+
+        let mut reservation = p.reserve().unwrap();
+        *reservation.slot() = MaybeUninit::new(Rc::new(0));
+        unsafe { reservation.commit() }
+
+        unsafe { p.enqueue_with(|slot| *slot = MaybeUninit::new(Rc::new(1))) }
+            .ok()
+            .unwrap();
+        assert!(p.reserve().is_none());
+        assert!(unsafe { p.enqueue_with(|_| unreachable!()) }.is_err());
+
+        assert_eq!(c.dequeue().map(|rc| *rc), Some(0));
+        assert_eq!(c.dequeue().map(|rc| *rc), Some(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn release_too_many() {
+        let mut rb: Queue<u8, U4> = Queue::new();
+
+        let (mut p, mut c) = rb.split();
+
+        p.enqueue(0).unwrap();
+        c.release(2);
+    }
 }
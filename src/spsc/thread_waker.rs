@@ -0,0 +1,32 @@
+use std::{
+    sync::Arc,
+    task::{RawWaker, RawWakerVTable, Waker},
+    thread::{self, Thread},
+};
+
+/// Returns a waker that unparks the current thread
+pub(crate) fn thread_waker() -> Waker {
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let thread = Arc::from_raw(data as *const Thread);
+        let clone = thread.clone();
+        let _ = Arc::into_raw(thread);
+        RawWaker::new(Arc::into_raw(clone) as *const (), &VTABLE)
+    }
+
+    unsafe fn wake(data: *const ()) {
+        Arc::from_raw(data as *const Thread).unpark();
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*(data as *const Thread)).unpark();
+    }
+
+    unsafe fn drop(data: *const ()) {
+        Arc::from_raw(data as *const Thread);
+    }
+
+    let thread = Arc::new(thread::current());
+    unsafe { Waker::from_raw(RawWaker::new(Arc::into_raw(thread) as *const (), &VTABLE)) }
+}
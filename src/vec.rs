@@ -72,6 +72,24 @@ where
         }
     }
 
+    pub(crate) fn insert(&mut self, index: usize, item: T) -> Result<(), T> {
+        assert!(index <= self.len);
+
+        if self.is_full() {
+            return Err(item);
+        }
+
+        unsafe {
+            // shift the elements after `index` one slot to the right
+            let p = (self.buffer.as_mut_ptr() as *mut T).add(index);
+            ptr::copy(p, p.add(1), self.len - index);
+            ptr::write(p, item);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
     pub(crate) fn is_full(&self) -> bool {
         self.len == self.capacity()
     }
@@ -102,6 +120,19 @@ where
         self.len += 1;
     }
 
+    pub(crate) fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len);
+
+        unsafe {
+            // shift the elements after `index` one slot to the left
+            let p = (self.buffer.as_mut_ptr() as *mut T).add(index);
+            let item = ptr::read(p);
+            ptr::copy(p.add(1), p, self.len - index - 1);
+            self.len -= 1;
+            item
+        }
+    }
+
     unsafe fn swap_remove_unchecked(&mut self, index: usize) -> T {
         let length = self.len;
         debug_assert!(index < length);
@@ -2,10 +2,14 @@
 #![deny(rust_2018_idioms)]
 #![deny(warnings)]
 
-use std::{sync::mpsc, thread};
+use std::{mem::MaybeUninit, sync::mpsc, thread};
 
 use generic_array::typenum::Unsigned;
-use heapless::{consts::*, mpmc::Q64, spsc};
+use heapless::{
+    consts::*,
+    mpmc::{self, Q64},
+    spsc,
+};
 use scoped_threadpool::Pool;
 
 #[test]
@@ -118,6 +122,92 @@ fn contention() {
     assert!(rb.is_empty());
 }
 
+#[test]
+fn grant_contention() {
+    type N = U1024;
+
+    let mut rb: spsc::Queue<u8, N> = spsc::Queue::new();
+
+    {
+        let (mut p, mut c) = rb.split();
+
+        Pool::new(2).scoped(move |scope| {
+            scope.execute(move || {
+                let mut sent = 0;
+
+                while sent < 4 * N::to_usize() {
+                    let grant = p.write_grant();
+                    let n = grant.len();
+                    for (i, slot) in grant.iter_mut().enumerate() {
+                        *slot = MaybeUninit::new((sent + i) as u8);
+                    }
+                    unsafe { p.commit(n) }
+                    sent += n;
+                }
+            });
+
+            scope.execute(move || {
+                let mut received = 0;
+
+                while received < 4 * N::to_usize() {
+                    let grant = c.read_grant();
+                    let n = grant.len();
+                    for (i, byte) in grant.iter().enumerate() {
+                        assert_eq!(*byte, (received + i) as u8);
+                    }
+                    c.release(n);
+                    received += n;
+                }
+            });
+        });
+    }
+
+    assert!(rb.is_empty());
+}
+
+#[test]
+fn slice_contention() {
+    type N = U1024;
+
+    let mut rb: spsc::Queue<u8, N> = spsc::Queue::new();
+
+    {
+        let (mut p, mut c) = rb.split();
+
+        Pool::new(2).scoped(move |scope| {
+            scope.execute(move || {
+                let items = (0..=255).collect::<Vec<u8>>();
+                let mut sent = 0;
+
+                while sent < 4 * N::to_usize() {
+                    let start = sent % items.len();
+                    sent += p.enqueue_slice(&items[start..]);
+                }
+            });
+
+            scope.execute(move || {
+                let mut buf = [0; 100];
+                let mut received = 0;
+
+                while received < 4 * N::to_usize() {
+                    let n = c.dequeue_into(&mut buf);
+                    for (i, byte) in buf[..n].iter().enumerate() {
+                        assert_eq!(*byte, (received + i) as u8);
+                    }
+                    received += n;
+
+                    for byte in c.drain() {
+                        assert_eq!(byte, received as u8);
+                        received += 1;
+                    }
+                }
+            });
+        });
+    }
+
+    assert!(rb.is_empty());
+}
+
 #[test]
 fn mpmc_contention() {
     const N: u32 = 64;
@@ -161,6 +251,304 @@ fn mpmc_contention() {
     assert_eq!(r.recv().unwrap(), r.recv().unwrap());
 }
 
+#[test]
+fn mpmc_generic_contention() {
+    const N: u32 = 512;
+
+    static Q: mpmc::Queue<u32, U512> = mpmc::Queue::NEW;
+
+    let (s, r) = mpsc::channel();
+    Pool::new(2).scoped(|scope| {
+        let s1 = s.clone();
+        scope.execute(move || {
+            let mut sum: u32 = 0;
+
+            for i in 0..(16 * N) {
+                sum = sum.wrapping_add(i);
+                while let Err(_) = Q.enqueue(i) {}
+            }
+
+            s1.send(sum).unwrap();
+        });
+
+        let s2 = s.clone();
+        scope.execute(move || {
+            let mut sum: u32 = 0;
+
+            for _ in 0..(16 * N) {
+                loop {
+                    match Q.dequeue() {
+                        Some(v) => {
+                            sum = sum.wrapping_add(v);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            s2.send(sum).unwrap();
+        });
+    });
+
+    assert_eq!(r.recv().unwrap(), r.recv().unwrap());
+}
+
+#[test]
+fn mpsc_contention() {
+    const N: u32 = 64;
+
+    static Q: heapless::mpsc::Queue<u32, U64> = heapless::mpsc::Queue::NEW;
+
+    let (s, r) = mpsc::channel();
+    Pool::new(3).scoped(|scope| {
+        for _ in 0..2 {
+            let s = s.clone();
+            scope.execute(move || {
+                let mut sum: u32 = 0;
+
+                for i in 0..(16 * N) {
+                    sum = sum.wrapping_add(i);
+                    while let Err(_) = Q.enqueue(i) {}
+                }
+
+                s.send(sum).unwrap();
+            });
+        }
+
+        let s = s.clone();
+        scope.execute(move || {
+            let mut consumer = Q.consumer().unwrap();
+            let mut sum: u32 = 0;
+
+            for _ in 0..(2 * 16 * N) {
+                loop {
+                    match consumer.dequeue() {
+                        Some(v) => {
+                            sum = sum.wrapping_add(v);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            s.send(sum).unwrap();
+        });
+    });
+
+    let mut sums = [r.recv().unwrap(), r.recv().unwrap(), r.recv().unwrap()];
+    sums.sort();
+    // the consumer's sum is the sum of both producers' sums
+    assert_eq!(sums[0], sums[1]);
+    assert_eq!(sums[2], sums[0].wrapping_add(sums[1]));
+}
+
+#[test]
+fn broadcast_contention() {
+    const N: u64 = 1024;
+
+    let mut q: heapless::broadcast::Queue<[u64; 4], U8> = heapless::broadcast::Queue::new();
+    let (mut w, r) = q.split();
+
+    Pool::new(3).scoped(|scope| {
+        for _ in 0..2 {
+            let mut r = r.clone();
+            scope.execute(move || {
+                let mut expected = 0;
+
+                while expected < N {
+                    match r.read() {
+                        Ok(Some(item)) => {
+                            // torn items must never be returned
+                            assert_eq!(item, [expected; 4]);
+                            expected += 1;
+                        }
+                        Ok(None) => {}
+                        Err(heapless::broadcast::Overrun(skipped)) => expected += skipped as u64,
+                    }
+                }
+            });
+        }
+
+        scope.execute(move || {
+            for i in 0..N {
+                w.write([i; 4]);
+            }
+        });
+    });
+}
+
+#[test]
+fn pool_contention() {
+    const N: usize = 1024;
+
+    static mut MEMORY: [u8; 256] = [0; 256];
+    static POOL: heapless::pool::Pool<[usize; 4]> = heapless::pool::Pool::new();
+
+    // few blocks and many threads to maximize the chances of running into the ABA problem
+    let blocks = POOL.grow(unsafe { &mut MEMORY });
+    assert!(blocks >= 2);
+
+    Pool::new(4).scoped(|scope| {
+        for _ in 0..4 {
+            scope.execute(move || {
+                for i in 0..N {
+                    if let Some(block) = POOL.alloc() {
+                        let block = block.init([i; 4]);
+                        assert_eq!(*block, [i; 4]);
+                        POOL.free(block);
+                    }
+                }
+            });
+        }
+    });
+
+    // no block was lost or handed out twice
+    let mut claimed = std::vec::Vec::new();
+    while let Some(block) = POOL.alloc() {
+        claimed.push(block);
+    }
+    assert_eq!(claimed.len(), blocks);
+}
+
+#[test]
+fn pool_singleton_contention() {
+    use heapless::{pool, pool::singleton::Pool as _};
+
+    const N: usize = 1024;
+
+    pool!(P: [u8; 16]);
+
+    static mut MEMORY: [u8; 128] = [0; 128];
+    let blocks = P::grow(unsafe { &mut MEMORY });
+
+    Pool::new(4).scoped(|scope| {
+        for _ in 0..4 {
+            scope.execute(move || {
+                for i in 0..N {
+                    if let Some(block) = P::alloc() {
+                        let block = block.init([i as u8; 16]);
+                        assert_eq!(*block, [i as u8; 16]);
+                        // returns the block to the pool
+                        drop(block);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut claimed = std::vec::Vec::new();
+    while let Some(block) = P::alloc() {
+        claimed.push(block);
+    }
+    assert_eq!(claimed.len(), blocks);
+}
+
+#[test]
+fn pool_arc_contention() {
+    use heapless::{
+        arc_pool,
+        pool::singleton::arc::{Arc, Pool as _},
+    };
+
+    const N: usize = 1024;
+
+    arc_pool!(P: [u8; 16]);
+
+    static mut MEMORY: [u8; 64] = [0; 64];
+    assert!(P::grow(unsafe { &mut MEMORY }) >= 1);
+
+    let shared = Arc::<P>::new([1; 16]).unwrap();
+
+    Pool::new(4).scoped(|scope| {
+        for _ in 0..4 {
+            let shared = &shared;
+            scope.execute(move || {
+                for _ in 0..N {
+                    let arc = shared.clone();
+                    assert_eq!(*arc, [1; 16]);
+                    drop(arc);
+                }
+            });
+        }
+    });
+
+    assert_eq!(Arc::strong_count(&shared), 1);
+    assert_eq!(Arc::try_unwrap(shared).ok(), Some([1; 16]));
+}
+
+#[test]
+fn pool_size_class_contention() {
+    use heapless::pool::{
+        size_class::{SizeClass, SizeClasses},
+        Pool as P,
+    };
+
+    const N: usize = 1024;
+
+    static A: SizeClasses<(P<SizeClass<[u8; 8]>>, P<SizeClass<[u8; 64]>>)> =
+        SizeClasses::new((P::new(), P::new()));
+
+    static mut SMALL: [u8; 64] = [0; 64];
+    static mut LARGE: [u8; 256] = [0; 256];
+    let blocks =
+        A.classes().0.grow(unsafe { &mut SMALL }) + A.classes().1.grow(unsafe { &mut LARGE });
+
+    Pool::new(4).scoped(|scope| {
+        for t in 0..4 {
+            scope.execute(move || {
+                for i in 0..N {
+                    let size = if (i + t) % 2 == 0 { 8 } else { 64 };
+                    if let Some(mut block) = A.alloc(size) {
+                        assert!(block.capacity() >= size);
+                        assert!(block.iter().all(|b| *b == 0));
+                        for b in block.iter_mut() {
+                            *b = t as u8 + 1;
+                        }
+                        // returns the block to its pool
+                        drop(block);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut claimed = std::vec::Vec::new();
+    while let Some(block) = A.alloc(8) {
+        claimed.push(block);
+    }
+    // every block went back to its own pool
+    assert_eq!(claimed.len(), blocks);
+}
+
+#[test]
+fn pool_size_class_fallback_contention() {
+    use heapless::pool::{
+        size_class::{SizeClass, SizeClasses},
+        Pool as P,
+    };
+
+    const N: usize = 1024;
+
+    static A: SizeClasses<(P<SizeClass<[u8; 8]>>, P<SizeClass<[u8; 32]>>)> =
+        SizeClasses::new((P::new(), P::new()));
+
+    static mut MEMORY: [u8; 256] = [0; 256];
+    A.classes().1.grow(unsafe { &mut MEMORY });
+
+    // every block comes from the second class, whose index must not corrupt the free list
+    Pool::new(4).scoped(|scope| {
+        for _ in 0..4 {
+            scope.execute(move || {
+                for _ in 0..N {
+                    drop(A.alloc(16));
+                }
+            });
+        }
+    });
+}
+
 #[test]
 fn unchecked() {
     type N = U1024;